@@ -1,19 +1,37 @@
-use std::io;
+use std::io::{self, Write};
 use std::sync::Arc;
+use std::time::Instant;
 
 use crossterm::event::KeyEvent;
 use lazy_static::lazy_static;
 use reqwest::{Client, ClientBuilder};
-use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
 
 use crate::components::comic_page::ComicPage;
+use crate::components::downloads_page::DownloadsPage;
+use crate::components::library_page::LibraryPage;
 use crate::components::loading_screen::LoadingScreen;
+use crate::components::logs_page::LogsPage;
 use crate::components::main_page::HomePage;
+use crate::components::notifications::{ErrorReport, Notifications};
+use crate::components::options_page::OptionsPage;
+use crate::components::reader_page::ReaderPage;
 use crate::components::search_page::SearchPage;
 use crate::config::Config;
+use crate::downloads::DownloadManager;
+use crate::graphics::{GraphicsProtocol, kitty};
+use crate::helpers::Shared;
+use crate::history::ReadingHistory;
+use crate::keymap::Keymap;
+use crate::library::{Library, LibraryEntry};
 use crate::models::comic::{Chapter, Comic, ComicInfo};
+use crate::models::sources::registry::SourceRegistry;
+use crate::operation::Operation;
+use crate::progress::ProgressUpdate;
+use crate::theme::Theme;
 use crate::traits::{Component, Source};
-use crate::tui::Tui;
+use crate::tui::{NikaEvent, Tui};
+use crate::{logging, paths};
 
 lazy_static! {
     pub static ref CLIENT: Client = ClientBuilder::new().gzip(true).build().unwrap();
@@ -25,11 +43,16 @@ pub enum Page {
     Home,
     Search,
     Options,
+    Library,
+    Downloads,
+    Logs,
     Comic(Comic, Arc<dyn Source>, ComicInfo),
+    /// Built-in reader for a downloaded chapter, stored at the given path.
+    Reader(Comic, Arc<dyn Source>, ComicInfo, Chapter, String),
     /// string: text shown to the user.
-    /// u16: progress of a given operation.
-    /// bool: displays gauge.
-    LoadingScreen(&'static str, Option<f64>, bool),
+    /// bool: displays the progress of a download.
+    /// Operation: what's being waited for, cancelled with Esc.
+    LoadingScreen(&'static str, bool, Operation),
 }
 
 #[derive(Default, Clone)]
@@ -41,32 +64,116 @@ pub enum InputMode {
 
 #[derive(Clone)]
 pub enum NikaAction {
-    Error,
+    /// Something failed. Shown as a notification over the current page.
+    Error(ErrorReport),
     Key(KeyEvent),
     Quit,
     Render,
+    /// Opens a page on top of the current one. Going home clears the history instead.
     ChangePage(Page),
+    /// Closes the operation's loading screen once it's done, opening the page in its place if
+    /// there's one. Does nothing if the user already left it, so a late task can't close
+    /// whatever page is on top by then.
+    LeaveLoadingScreen(Operation, Option<Page>),
+    /// Goes back to the previous page, as it was left.
+    Back,
+    /// Sends a failed action again to the page with the id, from its notification.
+    Retry(u64, Box<NikaAction>),
     SearchComic(String),
     SetSearchResults(Vec<Comic>),
     SelectComic(Comic),
     FetchNewChapters(bool), // true if right, false if left.
     SetChapters(Vec<Chapter>),
     FetchChapter(Chapter),
-    UpdateLoadingScreen(String, f64),
+    OpenLibraryEntry(LibraryEntry),
+    Progress(ProgressUpdate),
+    /// Text for the current page to show, e.g. a warning or why the viewer failed.
+    ShowMessage(String),
+    /// The options page changed the config. Every page gets the new one.
+    ConfigChanged(Config),
+    /// Adds chapters of a comic to the download queue.
+    QueueDownloads(Comic, Arc<dyn Source>, ComicInfo, Vec<Chapter>),
+}
+
+impl NikaAction {
+    /// The variant's name, for the log.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NikaAction::Error(_) => "error",
+            NikaAction::Key(_) => "key",
+            NikaAction::Quit => "quit",
+            NikaAction::Render => "render",
+            NikaAction::ChangePage(_) => "change_page",
+            NikaAction::LeaveLoadingScreen(..) => "leave_loading_screen",
+            NikaAction::Back => "back",
+            NikaAction::Retry(..) => "retry",
+            NikaAction::SearchComic(_) => "search_comic",
+            NikaAction::SetSearchResults(_) => "set_search_results",
+            NikaAction::SelectComic(_) => "select_comic",
+            NikaAction::FetchNewChapters(_) => "fetch_new_chapters",
+            NikaAction::SetChapters(_) => "set_chapters",
+            NikaAction::FetchChapter(_) => "fetch_chapter",
+            NikaAction::OpenLibraryEntry(_) => "open_library_entry",
+            NikaAction::Progress(_) => "progress",
+            NikaAction::ShowMessage(_) => "show_message",
+            NikaAction::ConfigChanged(_) => "config_changed",
+            NikaAction::QueueDownloads(..) => "queue_downloads",
+        }
+    }
 }
 
 pub struct App {
-    component: Box<dyn Component>,
+    /// Pages visited with their ids, the current one last. Home is always at the bottom.
+    pages: Vec<(u64, Box<dyn Component>)>,
+    next_page_id: u64,
     quit: bool,
     config: Config,
+    keymap: Keymap,
+    theme: Theme,
+    downloads: DownloadManager,
+    sources: SourceRegistry,
+    /// Shared by every page, so none of them saves over another's changes.
+    library: Shared<Library>,
+    history: Shared<ReadingHistory>,
+    notifications: Notifications,
 }
 
 impl App {
-    pub fn new(config: Config) -> Self {
+    /// `config_errors` are shown on the home page, see [`Config::load`]. So are conflicting key
+    /// bindings, invalid source definitions, and a library or history that couldn't be loaded.
+    pub fn new(config: Config, mut config_errors: Vec<String>) -> Self {
+        let (keymap, conflicts) = config.keymap();
+        config_errors.extend(conflicts);
+        let (theme, theme_error) = config.theme();
+        config_errors.extend(theme_error);
+        let (sources, source_errors) =
+            SourceRegistry::new(config.sources(), &paths::get().sources_dir());
+        config_errors.extend(source_errors);
+        // Left empty and never saved when broken, so the file can still be fixed by hand.
+        let library = Library::load().unwrap_or_else(|e| {
+            config_errors.push(format!("{e:#}"));
+            Library::default()
+        });
+        let history = ReadingHistory::load().unwrap_or_else(|e| {
+            config_errors.push(format!("{e:#}"));
+            ReadingHistory::default()
+        });
+
         Self {
-            component: Box::<HomePage>::default(),
+            pages: vec![(0, Box::new(HomePage::new(config_errors)))],
+            next_page_id: 1,
             quit: false,
+            downloads: DownloadManager::new(
+                config.download_concurrency(),
+                config.download_options(),
+            ),
+            sources,
+            library: Shared::new(library),
+            history: Shared::new(history),
             config,
+            keymap,
+            theme,
+            notifications: Notifications::default(),
         }
     }
 }
@@ -78,7 +185,7 @@ impl App {
         tui.init_panic_hook();
 
         let (tx, mut rx) = unbounded_channel::<NikaAction>();
-        self.component.init(tx.clone())?;
+        self.component().init(tx.clone())?;
 
         tui.run()?;
 
@@ -86,31 +193,119 @@ impl App {
             let event = tui.next().await;
 
             if let Some(e) = event {
-                // If there was an event in a given component.
-                if let Ok(Some(action)) = self.component.handle_events(Some(e)) {
+                // Notifications get the first look at keys.
+                let action = match e {
+                    NikaEvent::Key(key) => {
+                        let pages = &self.pages;
+                        let is_open = |id| pages.iter().any(|(page, _)| *page == id);
+                        self.notifications.handle_key(key, &self.keymap, is_open)
+                    }
+                    _ => None,
+                };
+                let action = match action {
+                    Some(action) => Ok(Some(action)),
+                    None => {
+                        let (_, component) = self.pages.last_mut().unwrap();
+                        component.handle_events(Some(e), &self.keymap)
+                    }
+                };
+
+                match action {
                     // ChangePage should be handled in the main loop
-                    tx.send(action).unwrap();
+                    Ok(Some(action)) => tx.send(action).unwrap(),
+                    Ok(None) => {}
+                    Err(e) => self
+                        .notifications
+                        .push(ErrorReport::new("Handling a key", e)),
                 }
             }
 
             // Action handler.
             while let Ok(act) = rx.try_recv() {
+                // Sent many times a second, so only worth logging when tracing.
+                if let NikaAction::Render | NikaAction::Progress(_) = act {
+                    tracing::trace!(action = act.name());
+                } else {
+                    tracing::debug!(action = act.name(), pages = self.pages.len());
+                }
+
                 match act {
-                    NikaAction::Quit => self.quit = true,
+                    NikaAction::Quit => {
+                        tracing::info!("quitting");
+                        self.quit = true;
+                    }
                     NikaAction::Render => {
                         // Receiving a render request causes the app to draw the widget on screen.
-                        tui.terminal.draw(|f| self.component.draw(f, f.size()))?;
+                        self.notifications.expire(Instant::now());
+
+                        let (_, component) = self.pages.last_mut().unwrap();
+                        let (theme, keymap) = (&self.theme, &self.keymap);
+                        let notifications = &self.notifications;
+                        tui.terminal.draw(|f| {
+                            component.draw(f, f.size(), theme, keymap);
+                            notifications.draw(f, f.size(), theme, keymap);
+                        })?;
                     }
 
+                    NikaAction::ChangePage(Page::Home) => {
+                        self.clear_screen(&mut tui)?;
+                        self.pages.truncate(1);
+                    }
                     NikaAction::ChangePage(page) => {
-                        let page = self.get_component(page);
-                        self.component = page;
-
-                        // Needs to be registered again after assigning a new component.
-                        self.component.init(tx.clone())?;
+                        self.clear_screen(&mut tui)?;
+                        self.open(page, &tx)?;
+                    }
+                    NikaAction::LeaveLoadingScreen(operation, page) => {
+                        if self.component().operation() == Some(&operation) {
+                            self.clear_screen(&mut tui)?;
+                            self.pages.pop();
+                            if let Some(page) = page {
+                                self.open(page, &tx)?;
+                            }
+                        }
+                    }
+                    NikaAction::Back => {
+                        if self.pages.len() > 1 {
+                            self.clear_screen(&mut tui)?;
+                            self.pages.pop();
+                        }
+                    }
+                    NikaAction::ConfigChanged(config) => {
+                        self.downloads
+                            .reconfigure(config.download_concurrency(), config.download_options());
+                        self.sources.reconfigure(config.sources());
+                        for (_, page) in &mut self.pages {
+                            if let Err(e) = page.update(NikaAction::ConfigChanged(config.clone())) {
+                                self.notifications
+                                    .push(ErrorReport::new("Applying the config", e));
+                            }
+                        }
+                        logging::set_level(config.log_level());
+                        (self.keymap, _) = config.keymap();
+                        (self.theme, _) = config.theme();
+                        self.config = config;
+                    }
+                    NikaAction::QueueDownloads(comic, source, info, chapters) => {
+                        self.downloads.enqueue(&comic, &info, source, chapters);
+                    }
+                    NikaAction::Error(report) => {
+                        let (page, _) = self.pages.last().unwrap();
+                        self.notifications.push(report.on_page(*page));
+                    }
+                    NikaAction::Retry(id, action) => {
+                        // Closed since the notification checked, so there's nothing to retry on.
+                        let Some((_, page)) = self.pages.iter_mut().find(|(p, _)| *p == id) else {
+                            continue;
+                        };
+                        if let Err(e) = page.update(*action) {
+                            self.notifications.push(ErrorReport::new("Retrying", e));
+                        }
                     }
                     _ => {
-                        self.component.update(act).unwrap();
+                        if let Err(e) = self.component().update(act) {
+                            self.notifications
+                                .push(ErrorReport::new("Updating the page", e));
+                        }
                     }
                 }
             }
@@ -123,13 +318,55 @@ impl App {
         Ok(())
     }
 
+    /// The page being shown.
+    fn component(&mut self) -> &mut Box<dyn Component> {
+        &mut self.pages.last_mut().unwrap().1
+    }
+
+    /// Puts a new page on top of the others.
+    fn open(&mut self, page: Page, tx: &UnboundedSender<NikaAction>) -> io::Result<()> {
+        let mut component = self.get_component(page);
+        component.init(tx.clone())?;
+        self.pages.push((self.next_page_id, component));
+        self.next_page_id += 1;
+        Ok(())
+    }
+
+    fn clear_screen(&self, tui: &mut Tui) -> io::Result<()> {
+        // Images drawn with escape sequences aren't part of ratatui's buffer, so they have to be
+        // wiped explicitly.
+        if self.config.reader().graphics.detect() == GraphicsProtocol::Kitty {
+            write!(tui.terminal.backend_mut(), "{}", kitty::DELETE_ALL)?;
+        }
+        tui.terminal.clear()
+    }
+
     fn get_component(&self, page: Page) -> Box<dyn Component> {
         match page {
             Page::Home => Box::<HomePage>::default(),
-            Page::Search => Box::<SearchPage>::default(),
-            Page::Options => todo!(),
-            Page::Comic(c, s, i) => Box::new(ComicPage::new(c, s, i, self.config.clone())),
-            Page::LoadingScreen(t, p, g) => Box::new(LoadingScreen::new(p, t, g)),
+            Page::Search => Box::new(SearchPage::new(self.sources.clone())),
+            Page::Options => Box::new(OptionsPage::new(self.config.clone())),
+            Page::Library => Box::new(LibraryPage::new(self.library.clone(), self.sources.clone())),
+            Page::Downloads => Box::new(DownloadsPage::new(self.downloads.clone())),
+            Page::Logs => Box::new(LogsPage::new(paths::get().state_dir.clone())),
+            Page::Comic(c, s, i) => Box::new(ComicPage::new(
+                c,
+                s,
+                i,
+                self.config.clone(),
+                self.library.clone(),
+                self.history.clone(),
+            )),
+            Page::Reader(c, s, i, ch, p) => Box::new(ReaderPage::new(
+                c,
+                s,
+                i,
+                ch,
+                &p,
+                self.config.clone(),
+                self.history.clone(),
+            )),
+            Page::LoadingScreen(t, g, o) => Box::new(LoadingScreen::new(t, g, o)),
         }
     }
 }
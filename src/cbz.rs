@@ -0,0 +1,226 @@
+use std::fs::{self, File};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use quick_xml::Writer;
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use zip::CompressionMethod;
+use zip::write::SimpleFileOptions;
+
+use crate::models::comic::{Chapter, Comic, ComicInfo, ComicType};
+use crate::viewer;
+
+/// Metadata read by Komga, Kavita and most e-readers.
+pub const COMIC_INFO: &str = "ComicInfo.xml";
+
+/// Builds ComicInfo.xml. The schema has no field for the publication status, so it goes into the
+/// notes.
+pub fn comic_info_xml(
+    comic: &Comic,
+    info: &ComicInfo,
+    chapter: &Chapter,
+    page_count: usize,
+) -> quick_xml::Result<String> {
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+
+    writer
+        .create_element("ComicInfo")
+        .with_attribute(("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance"))
+        .with_attribute(("xmlns:xsd", "http://www.w3.org/2001/XMLSchema"))
+        .write_inner_content::<_, quick_xml::Error>(|w| {
+            let mut field = |name: &str, value: &str| -> quick_xml::Result<()> {
+                if !value.is_empty() {
+                    w.create_element(name)
+                        .write_text_content(BytesText::new(value))?;
+                }
+                Ok(())
+            };
+
+            field("Title", &chapter.name)?;
+            field("Series", &comic.name)?;
+            field("Number", chapter_number(&chapter.name).unwrap_or_default())?;
+            // Only a plain year is valid here.
+            if info.date.parse::<u32>().is_ok() {
+                field("Year", &info.date)?;
+            }
+            field("Genre", &info.genres.join(", "))?;
+            if !info.status.is_empty() {
+                field("Notes", &format!("Status: {}", info.status))?;
+            }
+            field("Web", &comic.source)?;
+            field("PageCount", &page_count.to_string())?;
+            if comic.comic_type == ComicType::Manga {
+                field("Manga", "YesAndRightToLeft")?;
+            }
+            Ok(())
+        })?;
+
+    Ok(String::from_utf8_lossy(&writer.into_inner().into_inner()).into_owned())
+}
+
+/// The first number in a chapter's name, e.g. "10.5" in "Chapter 10.5: Title".
+pub fn chapter_number(name: &str) -> Option<&str> {
+    let start = name.find(|c: char| c.is_ascii_digit())?;
+    let rest = &name[start..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(rest.len());
+
+    Some(rest[..end].trim_end_matches('.'))
+}
+
+/// Where the archive of a chapter stored in `dir` goes: next to it, named after the chapter.
+pub fn cbz_path(dir: &Path) -> PathBuf {
+    let mut path = dir.to_owned().into_os_string();
+    path.push(".cbz");
+    PathBuf::from(path)
+}
+
+/// Packs the pages in `dir` into a CBZ archive with a ComicInfo.xml, unless it exists already.
+/// Returns the archive's path.
+pub fn export(
+    dir: &Path,
+    comic: &Comic,
+    info: &ComicInfo,
+    chapter: &Chapter,
+) -> anyhow::Result<PathBuf> {
+    let path = cbz_path(dir);
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let pages = viewer::pages(dir)?;
+    let xml = comic_info_xml(comic, info, chapter, pages.len())?;
+
+    // Written under another name first, so a failure never leaves a broken archive behind.
+    let mut partial = path.clone().into_os_string();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    let result = write_archive(&partial, &pages, &xml);
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+
+    fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+fn write_archive(path: &Path, pages: &[PathBuf], xml: &str) -> anyhow::Result<()> {
+    let mut zip = zip::ZipWriter::new(File::create(path)?);
+    // Images are compressed already.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    for page in pages {
+        let name = page
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid page name: {}", page.display()))?;
+
+        zip.start_file(name, stored)?;
+        zip.write_all(&fs::read(page)?)?;
+    }
+
+    zip.start_file(COMIC_INFO, SimpleFileOptions::default())?;
+    zip.write_all(xml.as_bytes())?;
+    zip.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Read;
+    use std::{env, fs};
+
+    use quick_xml::Reader;
+    use quick_xml::events::Event;
+
+    use super::{COMIC_INFO, chapter_number, export};
+    use crate::models::comic::{Chapter, Comic, ComicInfo, ComicType};
+
+    /// Text of every element directly below the root.
+    fn parse(xml: &str) -> HashMap<String, String> {
+        let mut reader = Reader::from_str(xml);
+        let mut fields = HashMap::new();
+        let mut current = None;
+
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) => {
+                    current = Some(String::from_utf8(e.name().as_ref().to_vec()).unwrap())
+                }
+                Event::Text(t) => {
+                    if let Some(name) = current.take() {
+                        fields.insert(name, t.unescape().unwrap().into_owned());
+                    }
+                }
+                Event::End(_) => current = None,
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        fields
+    }
+
+    #[test]
+    fn finds_chapter_numbers() {
+        assert_eq!(chapter_number("Chapter 10.5: Title"), Some("10.5"));
+        assert_eq!(chapter_number("Ch. 3."), Some("3"));
+        assert_eq!(chapter_number("Prologue"), None);
+    }
+
+    #[test]
+    fn packs_pages_and_metadata() {
+        let dir = env::temp_dir().join("nika-cbz-test").join("Chapter 2");
+        let _ = fs::remove_dir_all(dir.parent().unwrap());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("001.jpeg"), [1; 16]).unwrap();
+        fs::write(dir.join("002.png"), [2; 32]).unwrap();
+
+        let comic = Comic::new(
+            "Tom & Jerry <3",
+            "https://mangapill.com/manga/1/tom",
+            ComicType::Manga,
+            Vec::new(),
+        );
+        let info = ComicInfo::new("1999", "finished", vec!["Action".into(), "Comedy".into()]);
+        let chapter = Chapter::new("Chapter 2: The Return", "");
+
+        let path = export(&dir, &comic, &info, &chapter).unwrap();
+        assert!(path.ends_with("Chapter 2.cbz"));
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+        let names: Vec<_> = archive.file_names().collect();
+        assert_eq!(names, ["001.jpeg", "002.png", COMIC_INFO]);
+
+        let mut page = Vec::new();
+        archive
+            .by_name("002.png")
+            .unwrap()
+            .read_to_end(&mut page)
+            .unwrap();
+        assert_eq!(page, [2; 32]);
+
+        let mut xml = String::new();
+        archive
+            .by_name(COMIC_INFO)
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        let fields = parse(&xml);
+
+        assert_eq!(fields["Series"], "Tom & Jerry <3");
+        assert_eq!(fields["Title"], "Chapter 2: The Return");
+        assert_eq!(fields["Number"], "2");
+        assert_eq!(fields["Year"], "1999");
+        assert_eq!(fields["Genre"], "Action, Comedy");
+        assert_eq!(fields["Notes"], "Status: finished");
+        assert_eq!(fields["PageCount"], "2");
+        assert_eq!(fields["Manga"], "YesAndRightToLeft");
+    }
+}
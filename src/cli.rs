@@ -0,0 +1,719 @@
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use clap::{Args, Subcommand, ValueEnum};
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::cbz;
+use crate::config::Config;
+use crate::downloads::{self, DownloadOptions};
+use crate::helpers::MissingPages;
+use crate::library::Library;
+use crate::models::comic::{Chapter, Comic, ComicType};
+use crate::models::sources::registry::SourceRegistry;
+use crate::traits::Source;
+
+/// Commands that print their results instead of opening the TUI, e.g. to run from cron.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Searches a source for comics.
+    Search {
+        query: String,
+        /// Where to search.
+        #[arg(long, default_value = "mangapill")]
+        source: String,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Lists a comic's chapters, in the source's order.
+    Chapters {
+        #[command(flatten)]
+        comic: ComicArgs,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Shows a comic's status, date and genres.
+    Info {
+        #[command(flatten)]
+        comic: ComicArgs,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Downloads chapters of a comic into the download dir, oldest first.
+    Download {
+        #[command(flatten)]
+        comic: ComicArgs,
+        /// Chapter numbers, e.g. 1-10, 5, 12- or 1-3,7.
+        #[arg(long, value_name = "RANGE")]
+        chapters: ChapterRange,
+        /// Defaults to export_cbz from the config.
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+        /// The comic's name, which its folder is named after. Defaults to the one it has in the
+        /// library, or else in the source's search results, so the TUI finds the same folder.
+        #[arg(long)]
+        name: Option<String>,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Lists the sources, whether they're enabled and what they can do.
+    Sources {
+        #[command(flatten)]
+        output: Output,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct ComicArgs {
+    /// The comic's url, as printed by search.
+    url: String,
+    /// Its source. Guessed from the url when left out.
+    #[arg(long)]
+    source: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct Output {
+    /// Print JSON instead of text.
+    #[arg(long)]
+    json: bool,
+}
+
+/// How downloaded chapters are saved.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// A folder of images.
+    Dir,
+    /// A folder of images, also packed into a CBZ archive.
+    Cbz,
+}
+
+/// Chapter numbers to download, e.g. `1-10`, `5`, `12-` or `1-3,7`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterRange {
+    text: String,
+    /// Inclusive bounds.
+    spans: Vec<(f64, f64)>,
+}
+
+impl ChapterRange {
+    pub fn contains(&self, number: f64) -> bool {
+        self.spans
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&number))
+    }
+}
+
+impl FromStr for ChapterRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let number = |n: &str| {
+            n.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n >= 0.0)
+                .ok_or_else(|| anyhow::anyhow!("{n:?} isn't a chapter number"))
+        };
+
+        let spans = s
+            .split(',')
+            .map(|part| {
+                let (start, end) = match part.split_once('-') {
+                    Some((start, "")) => (number(start)?, f64::INFINITY),
+                    Some((start, end)) => (number(start)?, number(end)?),
+                    None => (number(part)?, number(part)?),
+                };
+                anyhow::ensure!(start <= end, "{part:?} ends before it starts");
+                Ok((start, end))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            text: s.to_owned(),
+            spans,
+        })
+    }
+}
+
+impl fmt::Display for ChapterRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[derive(Serialize)]
+struct ComicJson<'a> {
+    name: &'a str,
+    url: &'a str,
+    source: &'a str,
+}
+
+#[derive(Serialize)]
+struct ChapterJson<'a> {
+    name: &'a str,
+    url: &'a str,
+    /// As written in the name, e.g. "10.5".
+    number: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct InfoJson<'a> {
+    url: &'a str,
+    source: &'a str,
+    status: &'a str,
+    date: &'a str,
+    genres: &'a [String],
+}
+
+/// What happened to one chapter of a download.
+#[derive(Serialize)]
+struct DownloadReport {
+    chapter: String,
+    url: String,
+    /// "done", "partial", "failed" or "skipped".
+    status: &'static str,
+    /// The chapter's folder.
+    path: Option<String>,
+    /// The CBZ archive, when there's one.
+    archive: Option<String>,
+    /// Pages that couldn't be downloaded, starting from 1.
+    missing_pages: Vec<usize>,
+    error: Option<String>,
+}
+
+impl DownloadReport {
+    fn new(chapter: &Chapter, result: anyhow::Result<String>, options: &DownloadOptions) -> Self {
+        let mut report = Self {
+            chapter: chapter.name.to_owned(),
+            url: chapter.source.to_owned(),
+            status: "done",
+            path: None,
+            archive: None,
+            missing_pages: Vec::new(),
+            error: None,
+        };
+
+        match result {
+            Ok(path) => {
+                if options.cbz {
+                    let archive = cbz::cbz_path(Path::new(&path));
+                    report.archive = Some(archive.to_string_lossy().into_owned());
+                }
+                report.path = Some(path);
+            }
+            Err(e) => match e.downcast_ref::<MissingPages>() {
+                Some(missing) if missing.is_partial() => {
+                    report.status = "partial";
+                    report.path = Some(missing.dir.to_string_lossy().into_owned());
+                    report.missing_pages = missing.pages.clone();
+                    report.error = Some(missing.reason.to_owned());
+                }
+                _ => {
+                    report.status = "failed";
+                    report.error = Some(format!("{e:#}"));
+                }
+            },
+        }
+
+        report
+    }
+
+    /// A chapter that wasn't tried, since its name has no number to match against `range`.
+    fn skipped(chapter: &Chapter, range: &ChapterRange) -> Self {
+        Self {
+            chapter: chapter.name.to_owned(),
+            url: chapter.source.to_owned(),
+            status: "skipped",
+            path: None,
+            archive: None,
+            missing_pages: Vec::new(),
+            error: Some(format!("No chapter number to match against {range}")),
+        }
+    }
+
+    /// e.g. "done     Chapter 1  /home/nika/.local/share/nika-tui/downloads/...".
+    fn line(&self) -> String {
+        let detail = match self.status {
+            "failed" | "skipped" => self.error.clone().unwrap_or_default(),
+            "partial" => format!(
+                "{} ({} pages missing)",
+                self.path.as_deref().unwrap_or_default(),
+                self.missing_pages.len()
+            ),
+            _ => self
+                .archive
+                .clone()
+                .or_else(|| self.path.clone())
+                .unwrap_or_default(),
+        };
+        format!("{:8} {}  {detail}", self.status, self.chapter)
+    }
+}
+
+/// Runs a command, printing its results to `out`. Returns false when some of the work failed,
+/// e.g. a chapter that couldn't be downloaded.
+pub async fn run(
+    command: Command,
+    config: &Config,
+    sources: &SourceRegistry,
+    out: &mut impl Write,
+) -> anyhow::Result<bool> {
+    match command {
+        Command::Search {
+            query,
+            source,
+            output,
+        } => {
+            let source = sources.get(&source)?;
+            search(&*source, &query, output.json, out).await?;
+        }
+        Command::Chapters { comic, output } => {
+            let source = comic.source(sources)?;
+            chapters(&*source, &comic.url, output.json, out).await?;
+        }
+        Command::Info { comic, output } => {
+            let source = comic.source(sources)?;
+            info(&*source, &comic.url, output.json, out).await?;
+        }
+        Command::Download {
+            comic,
+            chapters,
+            format,
+            name,
+            output,
+        } => {
+            let source = comic.source(sources)?;
+            let mut options = config.download_options();
+            if let Some(format) = format {
+                options.cbz = format == Format::Cbz;
+            }
+            let name = match name {
+                Some(name) => name,
+                // Only read, so a library that can't be loaded is just no help.
+                None => {
+                    let library = Library::load().unwrap_or_default();
+                    comic_name(&*source, &library, &comic.url).await?
+                }
+            };
+            let comic = Comic::new(&name, &comic.url, ComicType::Manga, Vec::new());
+
+            let concurrency = config.download_concurrency();
+            return download(
+                &*source,
+                &comic,
+                &chapters,
+                &options,
+                concurrency,
+                output,
+                out,
+            )
+            .await;
+        }
+        Command::Sources { output } => list_sources(sources, output.json, out)?,
+    }
+
+    Ok(true)
+}
+
+impl ComicArgs {
+    /// The source given, or else the one whose site the url is on.
+    fn source(&self, sources: &SourceRegistry) -> anyhow::Result<Arc<dyn Source>> {
+        if let Some(name) = &self.source {
+            return sources.get(name);
+        }
+
+        sources
+            .for_url(&self.url)
+            .ok_or_else(|| anyhow::anyhow!("Can't tell the source of {}, use --source", self.url))
+    }
+}
+
+/// The last part of a url's path, e.g. "one-piece" for a Mangapill comic.
+fn default_name(url: &str) -> String {
+    let name = url.trim_end_matches('/').rsplit('/').next();
+    name.filter(|n| !n.is_empty()).unwrap_or(url).to_owned()
+}
+
+/// The name the TUI knows a comic by: its library entry's, or else the one the source's search
+/// gives it, looked up by the end of its url.
+async fn comic_name(source: &dyn Source, library: &Library, url: &str) -> anyhow::Result<String> {
+    let entry = library
+        .entries()
+        .iter()
+        .find(|e| e.source_name == source.name() && e.url == url);
+    if let Some(entry) = entry {
+        return Ok(entry.name.clone());
+    }
+
+    let query = default_name(url).replace('-', " ");
+    let found = source
+        .search(&query)
+        .await?
+        .into_iter()
+        .find(|c| c.source == url);
+    found.map(|c| c.name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Couldn't find the name of {url}, which its folder is named after, use --name"
+        )
+    })
+}
+
+fn write_json(out: &mut impl Write, value: &impl Serialize) -> anyhow::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn list_sources(sources: &SourceRegistry, json: bool, out: &mut impl Write) -> anyhow::Result<()> {
+    let sources = sources.list();
+    if json {
+        return write_json(out, &sources);
+    }
+
+    for source in sources {
+        let capabilities: Vec<_> = source
+            .capabilities
+            .iter()
+            .map(|c| format!("{c:?}").to_lowercase())
+            .collect();
+        let state = if source.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        };
+        writeln!(
+            out,
+            "{:12} {state:8} {}  {}",
+            source.id,
+            capabilities.join(","),
+            source.base_url
+        )?;
+    }
+    Ok(())
+}
+
+async fn search(
+    source: &dyn Source,
+    query: &str,
+    json: bool,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    let comics = source.search(query).await?;
+
+    if json {
+        let comics: Vec<ComicJson> = comics
+            .iter()
+            .map(|c| ComicJson {
+                name: &c.name,
+                url: &c.source,
+                source: source.name(),
+            })
+            .collect();
+        return write_json(out, &comics);
+    }
+
+    for comic in &comics {
+        writeln!(out, "{}  {}", comic.name, comic.source)?;
+    }
+    Ok(())
+}
+
+async fn chapters(
+    source: &dyn Source,
+    url: &str,
+    json: bool,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    let comic = Comic::new(&default_name(url), url, ComicType::Manga, Vec::new());
+    let chapters = source.get_chapters(&comic).await?;
+
+    if json {
+        let chapters: Vec<ChapterJson> = chapters
+            .iter()
+            .map(|c| ChapterJson {
+                name: &c.name,
+                url: &c.source,
+                number: cbz::chapter_number(&c.name),
+            })
+            .collect();
+        return write_json(out, &chapters);
+    }
+
+    for chapter in &chapters {
+        writeln!(out, "{}  {}", chapter.name, chapter.source)?;
+    }
+    Ok(())
+}
+
+async fn info(
+    source: &dyn Source,
+    url: &str,
+    json: bool,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    let comic = Comic::new(&default_name(url), url, ComicType::Manga, Vec::new());
+    let info = source
+        .get_info(&comic)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Couldn't find anything about {url}"))?;
+
+    if json {
+        return write_json(
+            out,
+            &InfoJson {
+                url,
+                source: source.name(),
+                status: &info.status,
+                date: &info.date,
+                genres: &info.genres,
+            },
+        );
+    }
+
+    writeln!(out, "Status: {}", info.status)?;
+    writeln!(out, "Date: {}", info.date)?;
+    writeln!(out, "Genres: {}", info.genres.join(", "))?;
+    Ok(())
+}
+
+/// Downloads the chapters of `comic` in `range`, `concurrency` at a time. Returns whether they
+/// all were. Chapters without a number can't be matched against `range`, so they're reported as
+/// skipped, which counts as not downloaded.
+async fn download(
+    source: &dyn Source,
+    comic: &Comic,
+    range: &ChapterRange,
+    options: &DownloadOptions,
+    concurrency: usize,
+    output: Output,
+    out: &mut impl Write,
+) -> anyhow::Result<bool> {
+    let info = source.get_info(comic).await?.unwrap_or_default();
+    let mut selected: Vec<(f64, Chapter)> = Vec::new();
+    // Reported instead of left out, e.g. MangaDex's "Chapter Oneshot".
+    let mut reports = Vec::new();
+    for chapter in source.get_chapters(comic).await? {
+        match cbz::chapter_number(&chapter.name).and_then(|n| n.parse().ok()) {
+            Some(number) if range.contains(number) => selected.push((number, chapter)),
+            Some(_) => {}
+            None => reports.push(DownloadReport::skipped(&chapter, range)),
+        }
+    }
+    anyhow::ensure!(
+        !selected.is_empty() || !reports.is_empty(),
+        "None of the chapters of {} are in {range}",
+        comic.name
+    );
+    if !output.json {
+        for report in &reports {
+            writeln!(out, "{}", report.line())?;
+        }
+    }
+    // Sources list the newest first.
+    selected.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    let info = &info;
+    let mut results = futures::stream::iter(selected)
+        .map(|(_, chapter)| async move {
+            let result =
+                downloads::fetch_chapter(source, comic, info, &chapter, options, None).await;
+            (chapter, result)
+        })
+        .buffered(concurrency.max(1));
+
+    while let Some((chapter, result)) = results.next().await {
+        let report = DownloadReport::new(&chapter, result, options);
+        if !output.json {
+            writeln!(out, "{}", report.line())?;
+        }
+        reports.push(report);
+    }
+
+    if output.json {
+        write_json(out, &reports)?;
+    }
+    Ok(reports.iter().all(|r| r.status == "done"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use serde_json::json;
+
+    use super::{ChapterRange, Output, comic_name, default_name, download, search};
+    use crate::app::CLIENT;
+    use crate::downloads::{DownloadOptions, chapter_dir};
+    use crate::library::{Library, LibraryEntry};
+    use crate::models::comic::{Chapter, Comic, ComicType};
+    use crate::models::sources::html::{ConfigurableHtmlSource, SourceDefinition};
+    use crate::test_utils::{self, MockResponse, MockServer};
+
+    #[test]
+    fn parses_chapter_ranges() {
+        let range: ChapterRange = "1-3,7,12-".parse().unwrap();
+
+        for n in [1.0, 2.5, 3.0, 7.0, 12.0, 1101.0] {
+            assert!(range.contains(n), "{n}");
+        }
+        for n in [0.0, 3.5, 6.0, 11.0] {
+            assert!(!range.contains(n), "{n}");
+        }
+        assert_eq!(range.to_string(), "1-3,7,12-");
+
+        for bad in ["", "ten", "10-1", "1-2-3", "-5"] {
+            assert!(bad.parse::<ChapterRange>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn names_comics_after_their_url() {
+        assert_eq!(
+            default_name("https://mangapill.com/manga/2/one-piece/"),
+            "one-piece"
+        );
+        assert_eq!(default_name("one-piece"), "one-piece");
+    }
+
+    #[tokio::test]
+    async fn prints_search_results_as_json() {
+        let server = test_utils::mangapill_server().await;
+        let source = ConfigurableHtmlSource::with_client(
+            SourceDefinition::mangapill().with_base_url(&server.url()),
+            CLIENT.clone(),
+        );
+
+        let mut out = Vec::new();
+        search(&source, "One Piece", true, &mut out).await.unwrap();
+
+        let printed: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            printed,
+            json!([
+                {
+                    "name": "One Piece",
+                    "url": format!("{}/manga/2/one-piece", server.url()),
+                    "source": "mangapill",
+                },
+                {
+                    "name": "One Piece Party",
+                    "url": format!("{}/manga/2262/one-piece-party", server.url()),
+                    "source": "mangapill",
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn finds_the_name_the_tui_uses() {
+        let server = test_utils::mangapill_server().await;
+        let source = ConfigurableHtmlSource::with_client(
+            SourceDefinition::mangapill().with_base_url(&server.url()),
+            CLIENT.clone(),
+        );
+        let url = format!("{}/manga/2/one-piece", server.url());
+        let mut library = Library::default();
+
+        // Found among One Piece Party and the rest by its url.
+        let name = comic_name(&source, &library, &url).await.unwrap();
+        assert_eq!(name, "One Piece");
+
+        let comic = Comic::new("One Piece (renamed)", &url, ComicType::Manga, Vec::new());
+        library.add(LibraryEntry::new(&comic, "mangapill", &Default::default()));
+        let name = comic_name(&source, &library, &url).await.unwrap();
+        assert_eq!(name, "One Piece (renamed)");
+
+        let unknown = format!("{}/manga/9/unknown", server.url());
+        let error = comic_name(&source, &library, &unknown).await.unwrap_err();
+        assert!(error.to_string().contains("--name"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn reports_chapters_without_a_number() {
+        let chapters = r#"<div><a class="border-border" href="/chapters/oneshot">Chapter Oneshot</a><a class="border-border" href="/chapters/2">Chapter 2</a></div>"#;
+        let server =
+            MockServer::start(vec![("/manga/9/short", MockResponse::html(chapters))]).await;
+        let source = ConfigurableHtmlSource::with_client(
+            SourceDefinition::mangapill().with_base_url(&server.url()),
+            CLIENT.clone(),
+        );
+        let url = format!("{}/manga/9/short", server.url());
+        let comic = Comic::new("Short", &url, ComicType::Manga, Vec::new());
+        let options = DownloadOptions {
+            root: env::temp_dir().join("nika-cli-test-skipped"),
+            cbz: false,
+        };
+
+        let mut out = Vec::new();
+        let range = "1".parse().unwrap();
+        let output = Output { json: false };
+        let done = download(&source, &comic, &range, &options, 1, output, &mut out)
+            .await
+            .unwrap();
+
+        assert!(!done);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "skipped  Chapter Oneshot  No chapter number to match against 1\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_every_chapter_downloaded() {
+        let server = test_utils::mangapill_server().await;
+        let source = ConfigurableHtmlSource::with_client(
+            SourceDefinition::mangapill().with_base_url(&server.url()),
+            CLIENT.clone(),
+        );
+        let comic = Comic::new(
+            "One Piece",
+            &format!("{}/manga/2/one-piece", server.url()),
+            ComicType::Manga,
+            Vec::new(),
+        );
+
+        let root = env::temp_dir().join("nika-cli-test");
+        let _ = fs::remove_dir_all(&root);
+        let options = DownloadOptions {
+            root: root.clone(),
+            cbz: false,
+        };
+
+        let mut out = Vec::new();
+        let range = "1100-1101".parse().unwrap();
+        let output = Output { json: true };
+        let done = download(&source, &comic, &range, &options, 2, output, &mut out)
+            .await
+            .unwrap();
+
+        // There's no page for chapter 1100.
+        assert!(!done);
+        let chapter_url = |path: &str| format!("{}/chapters/{path}", server.url());
+        let chapter = Chapter::new(
+            "Chapter 1101",
+            &chapter_url("2-11101000/one-piece-chapter-1101"),
+        );
+        let printed: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(printed[0]["chapter"], "Chapter 1100");
+        assert_eq!(printed[0]["status"], "failed");
+        assert_eq!(
+            printed[1],
+            json!({
+                "chapter": "Chapter 1101",
+                "url": chapter.source,
+                "status": "done",
+                "path": chapter_dir(&root, "mangapill", &comic, &chapter),
+                "archive": null,
+                "missing_pages": [],
+                "error": null,
+            })
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,19 +1,26 @@
+use std::collections::HashSet;
 use std::io;
+use std::path::Path;
 use std::sync::Arc;
 
-use crossterm::event::{self, KeyCode};
 use ratatui::prelude::*;
 use ratatui::symbols::border;
 use ratatui::widgets::block::*;
-use ratatui::widgets::{Borders, List, ListDirection, ListState, Paragraph, Wrap};
-use tokio::process::Command;
+use ratatui::widgets::{Borders, List, ListState, Paragraph, Wrap};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::app::{NikaAction, Page};
+use crate::components::notifications::ErrorReport;
 use crate::config::Config;
-use crate::helpers;
+use crate::helpers::{MissingPages, Shared};
+use crate::history::ReadingHistory;
+use crate::keymap::{Action, Context, Keymap};
+use crate::library::{Library, LibraryEntry};
 use crate::models::comic::{Chapter, Comic, ComicInfo};
+use crate::operation::Operation;
+use crate::theme::Theme;
 use crate::traits::{Component, Source};
+use crate::{downloads, helpers};
 
 pub struct ComicPage {
     action_tx: Option<UnboundedSender<NikaAction>>,
@@ -24,10 +31,25 @@ pub struct ComicPage {
     source: Arc<dyn Source>,
     info: ComicInfo,
     config: Config,
+    library: Shared<Library>,
+    history: Shared<ReadingHistory>,
+    /// Shown in the info panel, e.g. when a download or the viewer fails.
+    message: Option<String>,
+    /// Urls of the chapters marked for download.
+    marked: HashSet<String>,
+    /// Chapters that fit on screen.
+    height: usize,
 }
 
 impl ComicPage {
-    pub fn new(comic: Comic, source: Arc<dyn Source>, info: ComicInfo, config: Config) -> Self {
+    pub fn new(
+        comic: Comic,
+        source: Arc<dyn Source>,
+        info: ComicInfo,
+        config: Config,
+        library: Shared<Library>,
+        history: Shared<ReadingHistory>,
+    ) -> Self {
         let c = comic.clone();
         let chapters: Vec<Chapter> = c
             .chapters
@@ -44,8 +66,210 @@ impl ComicPage {
             source,
             info,
             config,
+            library,
+            history,
+            message: None,
+            marked: HashSet::new(),
+            height: 0,
         }
     }
+
+    fn final_page(&self) -> usize {
+        let page_size = self.config.chapter_page_size();
+        self.comic.chapters.len().div_ceil(page_size).max(1)
+    }
+
+    /// Shows the given page of chapters. Returns false if that page doesn't exist.
+    fn show_page(&mut self, page_number: usize) -> bool {
+        let amount = self.config.chapter_page_size();
+
+        let chapters = self
+            .comic
+            .chapters
+            .iter()
+            .skip((page_number - 1) * amount)
+            .take(amount)
+            .cloned()
+            .collect::<Vec<Chapter>>();
+
+        if chapters.is_empty() {
+            return false;
+        }
+
+        self.shown_chapters = chapters;
+        self.list_state.select(Some(0));
+        self.page_number = page_number;
+        true
+    }
+
+    fn selected_chapter(&self) -> Option<&Chapter> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.shown_chapters.get(i))
+    }
+
+    /// Queues the marked chapters, or the selected one if none are marked.
+    fn queue_marked(&mut self) -> Option<NikaAction> {
+        let chapters: Vec<Chapter> = match self.marked.is_empty() {
+            true => self.selected_chapter().cloned().into_iter().collect(),
+            // Oldest first, like they'd be read.
+            false => self
+                .comic
+                .chapters
+                .iter()
+                .rev()
+                .filter(|c| self.marked.contains(&c.source))
+                .cloned()
+                .collect(),
+        };
+        self.marked.clear();
+
+        self.queue(chapters)
+    }
+
+    fn queue_unread(&self) -> Option<NikaAction> {
+        let history = self.history.lock();
+        let progress = history.comic(self.source.name(), &self.comic.source);
+        let chapters = self
+            .comic
+            .chapters
+            .iter()
+            .rev()
+            .filter(|c| !progress.is_some_and(|p| p.is_read(c)))
+            .cloned()
+            .collect();
+
+        self.queue(chapters)
+    }
+
+    fn queue(&self, chapters: Vec<Chapter>) -> Option<NikaAction> {
+        (!chapters.is_empty()).then(|| {
+            NikaAction::QueueDownloads(
+                self.comic.clone(),
+                self.source.clone(),
+                self.info.clone(),
+                chapters,
+            )
+        })
+    }
+
+    /// Selects the first unread chapter after the furthest one read, switching pages if needed.
+    fn continue_reading(&mut self) {
+        let history = self.history.lock();
+        let progress = history.comic(self.source.name(), &self.comic.source);
+        let index = match progress {
+            Some(p) => p.resume_index(&self.comic.chapters),
+            // Nothing read yet, so start from the oldest chapter.
+            None => self.comic.chapters.len().checked_sub(1),
+        };
+        drop(history);
+
+        if let Some(index) = index {
+            let amount = self.config.chapter_page_size();
+
+            if self.show_page(index / amount + 1) {
+                self.list_state.select(Some(index % amount));
+            }
+        }
+    }
+}
+
+/// Downloads a chapter behind the loading screen, then shows it in the built-in reader or the
+/// configured viewer.
+pub fn open_chapter(
+    sender: UnboundedSender<NikaAction>,
+    source: Arc<dyn Source>,
+    comic: Comic,
+    info: ComicInfo,
+    chapter: Chapter,
+    config: &Config,
+) {
+    let viewer = config.viewer().clone();
+    let builtin_reader = config.reader().builtin;
+    let options = config.download_options();
+
+    Operation::spawn(|operation| async move {
+        sender
+            .send(NikaAction::ChangePage(Page::LoadingScreen(
+                "Downloading chapter",
+                true,
+                operation.clone(),
+            )))
+            .unwrap();
+        let result = downloads::fetch_chapter(
+            &*source,
+            &comic,
+            &info,
+            &chapter,
+            &options,
+            Some(sender.clone()),
+        )
+        .await;
+
+        // A chapter with a few missing pages is still worth reading.
+        let (path, warning) = match result {
+            Ok(path) => (path, None),
+            Err(e) => match e.downcast_ref::<MissingPages>() {
+                Some(missing) if missing.is_partial() => {
+                    let warning = format!(
+                        "{} downloaded with {} missing pages",
+                        chapter.name,
+                        missing.pages.len()
+                    );
+                    (missing.dir.to_string_lossy().into_owned(), Some(warning))
+                }
+                _ => {
+                    let report = ErrorReport::new(&format!("Downloading {}", chapter.name), e)
+                        .source(source.name())
+                        .retry(NikaAction::FetchChapter(chapter));
+                    let leave = NikaAction::LeaveLoadingScreen(operation, None);
+                    sender.send(leave).unwrap();
+                    sender.send(NikaAction::Error(report)).unwrap();
+                    return;
+                }
+            },
+        };
+
+        if builtin_reader {
+            let reader = Page::Reader(comic, source, info, chapter, path);
+            let leave = NikaAction::LeaveLoadingScreen(operation, Some(reader));
+            sender.send(leave).unwrap();
+            if let Some(warning) = warning {
+                sender.send(NikaAction::ShowMessage(warning)).unwrap();
+            }
+            return;
+        }
+
+        if viewer.wait {
+            let waiting =
+                Page::LoadingScreen("Waiting for the viewer to exit", false, operation.clone());
+            sender
+                .send(NikaAction::LeaveLoadingScreen(
+                    operation.clone(),
+                    Some(waiting),
+                ))
+                .unwrap();
+        } else {
+            sender
+                .send(NikaAction::LeaveLoadingScreen(operation.clone(), None))
+                .unwrap();
+        }
+
+        let result = viewer.open(Path::new(&path)).await;
+
+        if viewer.wait {
+            sender
+                .send(NikaAction::LeaveLoadingScreen(operation, None))
+                .unwrap();
+        }
+        if let Err(e) = result {
+            let report =
+                ErrorReport::new("Opening the viewer", e).retry(NikaAction::FetchChapter(chapter));
+            sender.send(NikaAction::Error(report)).unwrap();
+        } else if let Some(warning) = warning {
+            sender.send(NikaAction::ShowMessage(warning)).unwrap();
+        }
+    });
 }
 
 impl Component for ComicPage {
@@ -54,45 +278,54 @@ impl Component for ComicPage {
         Ok(())
     }
 
-    fn handle_key_events(&mut self, key: event::KeyEvent) -> io::Result<Option<NikaAction>> {
-        match key.code {
-            KeyCode::Char('q') => Ok(Some(NikaAction::Quit)),
-            KeyCode::Char('s') => Ok(Some(NikaAction::ChangePage(Page::Search))),
-            KeyCode::Char('h') => Ok(Some(NikaAction::ChangePage(Page::Home))),
-            KeyCode::Esc => Ok(Some(NikaAction::ChangePage(Page::Search))),
+    fn context(&self) -> Context {
+        Context::Comic
+    }
 
-            KeyCode::Up => {
-                let selected = self.list_state.selected().unwrap_or_default();
+    fn handle_action(&mut self, action: Action) -> io::Result<Option<NikaAction>> {
+        let selected = self.list_state.selected().unwrap_or_default();
+        let len = self.shown_chapters.len();
+        if let Some(index) = helpers::navigate(selected, len, self.height, action) {
+            self.list_state.select(Some(index));
+            return Ok(None);
+        }
 
-                let index = helpers::get_new_selection_index(
-                    selected,
-                    self.config.chapter_page_size(),
-                    ListDirection::BottomToTop,
-                );
-                self.list_state.select(Some(index));
+        match action {
+            Action::Quit => Ok(Some(NikaAction::Quit)),
+            Action::Search => Ok(Some(NikaAction::ChangePage(Page::Search))),
+            Action::Home => Ok(Some(NikaAction::ChangePage(Page::Home))),
+
+            Action::ToggleLibrary => {
+                let entry = LibraryEntry::new(&self.comic, self.source.name(), &self.info);
+                let mut library = self.library.lock();
+                library.toggle(entry);
+                library.save()?;
                 Ok(None)
             }
 
-            KeyCode::Down => {
-                let selected = self.list_state.selected().unwrap_or_default();
-
-                let index = helpers::get_new_selection_index(
-                    selected,
-                    self.shown_chapters.len(),
-                    ListDirection::TopToBottom,
-                );
+            Action::ContinueReading => {
+                self.continue_reading();
+                Ok(None)
+            }
 
-                self.list_state.select(Some(index));
+            Action::Mark => {
+                if let Some(url) = self.selected_chapter().map(|c| c.source.clone()) {
+                    if !self.marked.remove(&url) {
+                        self.marked.insert(url);
+                    }
+                }
                 Ok(None)
             }
+            Action::DownloadMarked => Ok(self.queue_marked()),
+            Action::DownloadUnread => Ok(self.queue_unread()),
+            Action::Downloads => Ok(Some(NikaAction::ChangePage(Page::Downloads))),
 
-            KeyCode::Right => Ok(Some(NikaAction::FetchNewChapters(true))),
-            KeyCode::Left => Ok(Some(NikaAction::FetchNewChapters(false))),
+            Action::NextPage => Ok(Some(NikaAction::FetchNewChapters(true))),
+            Action::PreviousPage => Ok(Some(NikaAction::FetchNewChapters(false))),
 
-            KeyCode::Enter => {
-                let chapter = self.shown_chapters[self.list_state.selected().unwrap()].clone();
-                Ok(Some(NikaAction::FetchChapter(chapter)))
-            }
+            Action::Select => Ok(self
+                .selected_chapter()
+                .map(|c| NikaAction::FetchChapter(c.to_owned()))),
             _ => Ok(None),
         }
     }
@@ -100,11 +333,9 @@ impl Component for ComicPage {
     fn update(&mut self, action: NikaAction) -> anyhow::Result<()> {
         match action {
             NikaAction::FetchNewChapters(a) => {
-                let final_page = (self.comic.chapters.len() as f32 / 25_f32).ceil() as usize;
-
                 let new_page_number = match a {
                     true => {
-                        if self.page_number == final_page {
+                        if self.page_number == self.final_page() {
                             self.page_number
                         } else {
                             self.page_number + 1
@@ -119,50 +350,40 @@ impl Component for ComicPage {
                     }
                 };
 
-                let amount = self.config.chapter_page_size();
-
-                let tmp = self.comic.chapters.clone();
-                let chapters = tmp
-                    .into_iter()
-                    .skip((new_page_number - 1) * amount)
-                    .take(amount)
-                    .collect::<Vec<Chapter>>();
-
-                if chapters.is_empty() {
-                    return Ok(());
-                }
-
-                self.shown_chapters = chapters;
-                self.list_state.select(Some(0));
-                self.page_number = new_page_number;
+                self.show_page(new_page_number);
             }
 
             NikaAction::SetChapters(chapters) => self.comic.chapters = chapters,
 
             NikaAction::FetchChapter(chap) => {
-                let sender = self.action_tx.clone().unwrap();
-                let source = self.source.clone();
-                let info = self.info.clone();
-                let comic = self.comic.clone();
-
-                tokio::spawn(async move {
-                    sender
-                        .send(NikaAction::ChangePage(Page::LoadingScreen(
-                            "Downloading chapter",
-                            None,
-                            true
-                        )))
-                        .unwrap();
-                    match source.download_chapter(&chap, Some(sender.clone())).await {
-                        Ok(path) => {
-                            sender
-                                .send(NikaAction::ChangePage(Page::Comic(comic, source, info)))
-                                .unwrap();
-                            Command::new("feh").args([path]).output().await.unwrap();
-                        }
-                        Err(e) => panic!("{:?}", e), // temporary lol
-                    }
-                });
+                let mut history = self.history.lock();
+                history.mark_read(self.source.name(), &self.comic.source, &chap);
+                // A history that can't be saved, e.g. a broken file reported on startup, isn't
+                // worth keeping anyone from reading.
+                let _ = history.save();
+                drop(history);
+
+                open_chapter(
+                    self.action_tx.clone().unwrap(),
+                    self.source.clone(),
+                    self.comic.clone(),
+                    self.info.clone(),
+                    chap,
+                    &self.config,
+                );
+            }
+
+            NikaAction::ShowMessage(message) => self.message = Some(message),
+            NikaAction::ConfigChanged(config) => {
+                // Stays on the same chapter with the new page size.
+                let index = (self.page_number - 1) * self.config.chapter_page_size()
+                    + self.list_state.selected().unwrap_or_default();
+                self.config = config;
+
+                let amount = self.config.chapter_page_size();
+                if self.show_page(index / amount + 1) {
+                    self.list_state.select(Some(index % amount));
+                }
             }
             _ => {}
         };
@@ -170,7 +391,7 @@ impl Component for ComicPage {
         Ok(())
     }
 
-    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect) {
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
         let main_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(vec![Constraint::Percentage(25), Constraint::Percentage(75)])
@@ -184,40 +405,86 @@ impl Component for ComicPage {
         let block = Block::default()
             .borders(Borders::ALL)
             .border_set(border::ROUNDED)
-            .style(Style::new().fg(Color::Green))
+            .border_style(theme.border())
             .title_alignment(Alignment::Center);
 
+        let saved = self
+            .library
+            .lock()
+            .contains(self.source.name(), &self.comic.source);
+        let library_hint = match saved {
+            true => "remove from library",
+            false => "add to library",
+        };
+        let library_hint = keymap.hints(Context::Comic, &[(Action::ToggleLibrary, library_hint)]);
+
         let paragraph = Paragraph::new(Text::from(self.comic.name.to_owned().bold()))
             .centered()
-            .block(block.clone());
+            .block(block.clone().title_bottom(library_hint));
 
-        let more_info = Paragraph::new(vec![
+        let mut lines: Vec<Line> = vec![
             format!("Year: {}", self.info.date.to_string().bold()).into(),
             format!("Genres: {}", self.info.genres.join(", ").bold()).into(),
-        ])
-        .centered()
-        .block(block.clone())
-        .wrap(Wrap { trim: true });
+        ];
+        if let Some(message) = &self.message {
+            lines.push(Line::default());
+            lines.push(Line::from(message.as_str()).style(theme.error()));
+        }
 
-        let page_size = self.config.chapter_page_size();
-        let total_pages = (self.comic.chapters.len() as f32 / page_size as f32).ceil();
-        let tmp = format!("Chapters (Page {} of {})", self.page_number, total_pages);
+        let more_info = Paragraph::new(lines)
+            .centered()
+            .block(block.clone())
+            .wrap(Wrap { trim: true });
+
+        let tmp = format!(
+            "Chapters (Page {} of {})",
+            self.page_number,
+            self.final_page()
+        );
+        let history = self.history.lock();
+        let progress = history.comic(self.source.name(), &self.comic.source);
 
         let list = self
             .shown_chapters
             .iter()
-            .map(|f| Text::from(f.name.as_str()))
+            .map(|f| {
+                let mark = match self.marked.contains(&f.source) {
+                    true => "[x] ",
+                    false => "",
+                };
+
+                match progress.and_then(|p| p.chapter(&f.source)) {
+                    Some(read) => {
+                        let name = match read.last_page {
+                            Some(page) => format!("{mark}{} (page {page})", f.name),
+                            None => format!("{mark}{}", f.name),
+                        };
+                        Text::from(name).style(theme.dimmed())
+                    }
+                    None => Text::from(format!("{mark}{}", f.name)),
+                }
+            })
             .collect::<List>()
-            .block(
-                block
-                    .title(tmp)
-                    .title_bottom("◀ previous, ▲ up, ▼ down, ▶ next"),
-            )
-            .style(Style::new().fg(Color::White))
-            .highlight_style(Style::new().fg(Color::LightGreen));
+            .block(block.title(tmp).title_bottom(keymap.hints(
+                Context::Comic,
+                &[
+                    (Action::PreviousPage, "previous"),
+                    (Action::Up, "up"),
+                    (Action::Down, "down"),
+                    (Action::NextPage, "next"),
+                    (Action::ContinueReading, "continue reading"),
+                    (Action::Mark, "mark"),
+                    (Action::DownloadMarked, "download"),
+                    (Action::DownloadUnread, "download unread"),
+                    (Action::Downloads, "downloads"),
+                    (Action::Back, "back"),
+                ],
+            )))
+            .highlight_style(theme.highlight());
 
         f.render_widget(paragraph, inner_layout[0]);
         f.render_widget(more_info, inner_layout[1]);
+        self.height = main_layout[1].height.saturating_sub(2) as usize;
         f.render_stateful_widget(list, main_layout[1], &mut self.list_state);
     }
 }
@@ -0,0 +1,201 @@
+use std::io;
+
+use ratatui::prelude::*;
+use ratatui::widgets::block::*;
+use ratatui::widgets::{Borders, List, ListItem, ListState};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::app::{NikaAction, Page};
+use crate::downloads::{DownloadManager, DownloadState};
+use crate::helpers;
+use crate::keymap::{Action, Context, Keymap};
+use crate::theme::Theme;
+use crate::traits::Component;
+
+/// Width of the text progress bar, in characters.
+const BAR_WIDTH: usize = 20;
+
+/// Lists queued downloads. They keep going in the background after leaving this page.
+pub struct DownloadsPage {
+    action_tx: Option<UnboundedSender<NikaAction>>,
+    downloads: DownloadManager,
+    list_state: ListState,
+    /// Downloads that fit on screen.
+    height: usize,
+}
+
+impl DownloadsPage {
+    pub fn new(downloads: DownloadManager) -> Self {
+        Self {
+            action_tx: None,
+            downloads,
+            list_state: ListState::default().with_selected(Some(0)),
+            height: 0,
+        }
+    }
+
+    /// Id of the selected download.
+    fn selected(&self) -> Option<usize> {
+        let index = self.list_state.selected()?;
+        self.downloads
+            .with_items(|items| items.get(index).map(|i| i.id))
+    }
+}
+
+fn progress_bar(progress: f64) -> String {
+    let filled = (progress.clamp(0.0, 1.0) * BAR_WIDTH as f64).round() as usize;
+    format!(
+        "[{}{}] {:>3}%",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        (progress * 100.0).round()
+    )
+}
+
+impl Component for DownloadsPage {
+    fn init(&mut self, tx: UnboundedSender<NikaAction>) -> io::Result<()> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn context(&self) -> Context {
+        Context::Downloads
+    }
+
+    fn handle_action(&mut self, action: Action) -> io::Result<Option<NikaAction>> {
+        let len = self.downloads.with_items(|items| items.len());
+
+        let selected = self.list_state.selected().unwrap_or_default();
+        if let Some(index) = helpers::navigate(selected, len, self.height, action) {
+            self.list_state.select(Some(index));
+            return Ok(None);
+        }
+
+        match action {
+            Action::Quit => Ok(Some(NikaAction::Quit)),
+            Action::Home => Ok(Some(NikaAction::ChangePage(Page::Home))),
+            Action::Search => Ok(Some(NikaAction::ChangePage(Page::Search))),
+
+            Action::Pause => {
+                if let Some(id) = self.selected() {
+                    self.downloads.toggle_pause(id);
+                }
+                Ok(None)
+            }
+            Action::Cancel => {
+                if let Some(id) = self.selected() {
+                    self.downloads.cancel(id);
+                }
+                Ok(None)
+            }
+            Action::Retry => {
+                if let Some(id) = self.selected() {
+                    self.downloads.retry(id);
+                }
+                Ok(None)
+            }
+            Action::ClearFinished => {
+                self.downloads.clear_finished();
+                let last = self
+                    .downloads
+                    .with_items(|items| items.len())
+                    .saturating_sub(1);
+                self.list_state
+                    .select(self.list_state.selected().map(|i| i.min(last)));
+                Ok(None)
+            }
+
+            Action::Select => {
+                let Some(index) = self.list_state.selected() else {
+                    return Ok(None);
+                };
+
+                // Finished chapters open in the reader.
+                Ok(self.downloads.with_items(|items| {
+                    let item = items.get(index)?;
+                    let (DownloadState::Done(path) | DownloadState::Partial(path, _)) = &item.state
+                    else {
+                        return None;
+                    };
+
+                    Some(NikaAction::ChangePage(Page::Reader(
+                        item.comic.clone(),
+                        item.source.clone(),
+                        item.info.clone(),
+                        item.chapter.clone(),
+                        path.clone(),
+                    )))
+                }))
+            }
+
+            _ => Ok(None),
+        }
+    }
+
+    fn update(&mut self, _action: NikaAction) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border())
+            .border_type(BorderType::Rounded)
+            .title("Downloads")
+            .title_alignment(Alignment::Center)
+            .title_bottom(keymap.hints(
+                Context::Downloads,
+                &[
+                    (Action::Select, "to read"),
+                    (Action::Pause, "pause/resume"),
+                    (Action::Cancel, "cancel"),
+                    (Action::Retry, "retry"),
+                    (Action::ClearFinished, "clear finished"),
+                    (Action::Home, "for home"),
+                    (Action::Back, "back"),
+                ],
+            ));
+
+        let items = self.downloads.with_items(|items| {
+            items
+                .iter()
+                .map(|i| {
+                    let name = format!("{} - {}", i.comic.name, i.chapter.name);
+                    let (status, style) = match &i.state {
+                        DownloadState::Queued => ("queued".to_owned(), Style::new()),
+                        DownloadState::Downloading => (progress_bar(i.progress), Style::new()),
+                        DownloadState::Paused => ("paused".to_owned(), theme.warning()),
+                        DownloadState::Done(_) => ("done".to_owned(), theme.accent()),
+                        DownloadState::Partial(_, missing) => (
+                            format!("done, {} missing pages", missing.len()),
+                            theme.warning(),
+                        ),
+                        DownloadState::Failed(e) => (format!("failed: {e}"), theme.error()),
+                        DownloadState::Cancelled => ("cancelled".to_owned(), theme.dimmed()),
+                    };
+
+                    ListItem::new(format!("{name}  {status}")).style(style)
+                })
+                .collect::<Vec<ListItem>>()
+        });
+
+        let list = if items.is_empty() {
+            let hint = keymap.hints(
+                Context::Comic,
+                &[(Action::DownloadMarked, "on a comic's chapters")],
+            );
+            let text = match hint.is_empty() {
+                true => "Nothing queued.".to_owned(),
+                false => format!("Nothing queued. Use {hint}."),
+            };
+            List::new([ListItem::new(text)])
+        } else {
+            List::new(items)
+        };
+
+        let list = list.block(block).highlight_style(theme.highlight());
+
+        self.height = rect.height.saturating_sub(2) as usize;
+        f.render_stateful_widget(list, rect, &mut self.list_state);
+    }
+}
@@ -0,0 +1,169 @@
+use std::io;
+
+use ratatui::prelude::*;
+use ratatui::widgets::block::*;
+use ratatui::widgets::{Borders, List, ListItem, ListState};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::app::{NikaAction, Page};
+use crate::components::notifications::ErrorReport;
+use crate::helpers::{self, Shared};
+use crate::keymap::{Action, Context, Keymap};
+use crate::library::Library;
+use crate::models::sources::registry::SourceRegistry;
+use crate::operation::Operation;
+use crate::theme::Theme;
+use crate::traits::Component;
+
+pub struct LibraryPage {
+    action_tx: Option<UnboundedSender<NikaAction>>,
+    library: Shared<Library>,
+    /// Where the comics are opened from.
+    sources: SourceRegistry,
+    list_state: ListState,
+    /// Entries that fit on screen.
+    height: usize,
+}
+
+impl LibraryPage {
+    pub fn new(library: Shared<Library>, sources: SourceRegistry) -> Self {
+        Self {
+            action_tx: None,
+            library,
+            sources,
+            list_state: ListState::default().with_selected(Some(0)),
+            height: 0,
+        }
+    }
+}
+
+impl Component for LibraryPage {
+    fn init(&mut self, tx: UnboundedSender<NikaAction>) -> io::Result<()> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn context(&self) -> Context {
+        Context::Library
+    }
+
+    fn handle_action(&mut self, action: Action) -> io::Result<Option<NikaAction>> {
+        let mut library = self.library.lock();
+        let entries = library.entries();
+
+        let selected = self.list_state.selected().unwrap_or_default();
+        if let Some(index) = helpers::navigate(selected, entries.len(), self.height, action) {
+            self.list_state.select(Some(index));
+            return Ok(None);
+        }
+
+        match action {
+            Action::Quit => Ok(Some(NikaAction::Quit)),
+            Action::Home => Ok(Some(NikaAction::ChangePage(Page::Home))),
+            Action::Search => Ok(Some(NikaAction::ChangePage(Page::Search))),
+
+            Action::Select => {
+                let entry = self.list_state.selected().and_then(|i| entries.get(i));
+                Ok(entry.map(|e| NikaAction::OpenLibraryEntry(e.to_owned())))
+            }
+
+            Action::Remove => {
+                if let Some(entry) = self.list_state.selected().and_then(|i| entries.get(i)) {
+                    let (source_name, url) = (entry.source_name.clone(), entry.url.clone());
+                    library.remove(&source_name, &url);
+                    library.save()?;
+
+                    let last = library.entries().len().saturating_sub(1);
+                    self.list_state
+                        .select(self.list_state.selected().map(|i| i.min(last)));
+                }
+                Ok(None)
+            }
+
+            _ => Ok(None),
+        }
+    }
+
+    fn update(&mut self, action: NikaAction) -> anyhow::Result<()> {
+        if let NikaAction::OpenLibraryEntry(entry) = action {
+            let sender = self.action_tx.as_ref().unwrap().to_owned();
+
+            let source = match self.sources.get(&entry.source_name) {
+                Ok(source) => source,
+                Err(e) => {
+                    sender.send(NikaAction::Error(
+                        ErrorReport::new("Opening the comic", e).source(&entry.source_name),
+                    ))?;
+                    return Ok(());
+                }
+            };
+
+            Operation::spawn(|operation| async move {
+                sender
+                    .send(NikaAction::ChangePage(Page::LoadingScreen(
+                        "Loading Comics...",
+                        false,
+                        operation.clone(),
+                    )))
+                    .unwrap();
+
+                let mut comic = entry.comic();
+
+                let action = match source.get_chapters(&comic).await {
+                    Ok(chapters) => {
+                        comic.chapters = chapters;
+                        let page = Page::Comic(comic, source, entry.info);
+                        NikaAction::LeaveLoadingScreen(operation, Some(page))
+                    }
+                    Err(e) => {
+                        let leave = NikaAction::LeaveLoadingScreen(operation, None);
+                        sender.send(leave).unwrap();
+                        NikaAction::Error(
+                            ErrorReport::new("Loading the chapters", e)
+                                .source(source.name())
+                                .retry(NikaAction::OpenLibraryEntry(entry)),
+                        )
+                    }
+                };
+
+                sender.send(action).unwrap();
+            });
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border())
+            .border_type(BorderType::Rounded)
+            .title("Library")
+            .title_alignment(Alignment::Center)
+            .title_bottom(keymap.hints(
+                Context::Library,
+                &[
+                    (Action::Select, "to open"),
+                    (Action::Remove, "to remove"),
+                    (Action::Search, "for search"),
+                    (Action::Home, "for home"),
+                    (Action::Back, "back"),
+                ],
+            ));
+
+        let items = self
+            .library
+            .lock()
+            .entries()
+            .iter()
+            .map(|e| ListItem::new(format!("{} ({})", e.name, e.source_name)))
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(theme.highlight());
+
+        self.height = rect.height.saturating_sub(2) as usize;
+        f.render_stateful_widget(list, rect, &mut self.list_state);
+    }
+}
@@ -1,34 +1,69 @@
 use std::io;
+use std::time::Instant;
 
-use crossterm::event::KeyEvent;
 use ratatui::layout::Rect;
-use ratatui::style::{Color, Style, Stylize};
+use ratatui::style::Stylize;
+use ratatui::text::Line;
 use ratatui::widgets::{Block, BorderType, Borders, LineGauge, Paragraph};
-use ratatui::{symbols, Frame};
+use ratatui::{Frame, symbols};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::app::NikaAction;
+use crate::keymap::{Action, Keymap};
+use crate::operation::Operation;
+use crate::progress::{self, Phase, Progress};
+use crate::theme::Theme;
 use crate::traits::Component;
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct LoadingScreen {
-    percentage: Option<f64>,
     text: String,
     sender: Option<UnboundedSender<NikaAction>>,
-    operation: String,
+    progress: Progress,
+    /// When pages started downloading, for the speed and ETA.
+    started: Option<Instant>,
     show_gauge: bool,
+    operation: Operation,
 }
 
 impl LoadingScreen {
-    pub fn new(progress: Option<f64>, text: &str, gauge: bool) -> Self {
+    pub fn new(text: &str, gauge: bool, operation: Operation) -> Self {
         Self {
-            percentage: progress,
             text: text.to_owned(),
             sender: None,
-            operation: String::from(""),
-            show_gauge: gauge
+            progress: Progress::default(),
+            started: None,
+            show_gauge: gauge,
+            operation,
         }
     }
+
+    /// e.g. "Page 3 of 20 | 1.2 MiB of 4.0 MiB | 512.0 KiB/s | 6s left".
+    fn details(&self) -> String {
+        let p = &self.progress;
+        if p.phase != Phase::Downloading {
+            return String::new();
+        }
+
+        let mut parts = vec![format!("Page {} of {}", p.pages_done, p.page_count)];
+
+        let received = progress::format_bytes(p.bytes as f64);
+        parts.push(match p.total_bytes() {
+            Some(total) => format!("{received} of {}", progress::format_bytes(total as f64)),
+            None => received,
+        });
+
+        if let Some(elapsed) = self.started.map(|s| s.elapsed()) {
+            if let Some(speed) = p.speed(elapsed) {
+                parts.push(format!("{}/s", progress::format_bytes(speed)));
+            }
+            if let Some(eta) = p.eta(elapsed) {
+                parts.push(format!("{} left", progress::format_duration(eta)));
+            }
+        }
+
+        parts.join(" | ")
+    }
 }
 
 impl Component for LoadingScreen {
@@ -37,29 +72,40 @@ impl Component for LoadingScreen {
         Ok(())
     }
 
-    fn handle_key_events(&mut self, key: KeyEvent) -> io::Result<Option<NikaAction>> {
+    /// Only Back does anything here, leaving would keep the operation running.
+    fn handle_action(&mut self, _action: Action) -> io::Result<Option<NikaAction>> {
         Ok(None)
     }
 
+    /// Whoever started the operation is the previous page.
+    fn back(&mut self) -> Option<NikaAction> {
+        self.operation.cancel();
+        Some(NikaAction::Back)
+    }
+
+    fn operation(&self) -> Option<&Operation> {
+        Some(&self.operation)
+    }
+
     fn update(&mut self, action: crate::app::NikaAction) -> anyhow::Result<()> {
         match action {
-            NikaAction::UpdateLoadingScreen(operation, pr) => {
-                let mut percentage = self.percentage.unwrap_or_default();
-                percentage += pr;
-
-                self.percentage = Some(percentage);
-                self.operation = operation;
+            NikaAction::Progress(update) => {
+                self.progress.apply(update);
+                if self.progress.phase == Phase::Downloading && self.started.is_none() {
+                    self.started = Some(Instant::now());
+                }
                 Ok(())
             }
             _ => Ok(()),
         }
     }
 
-    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect) {
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
         let block = Block::default()
-            .border_style(Style::new().light_blue())
+            .border_style(theme.border())
             .borders(Borders::ALL)
-            .border_type(BorderType::Rounded);
+            .border_type(BorderType::Rounded)
+            .title_bottom(keymap.hints(self.context(), &[(Action::Back, "to cancel")]));
 
         let p = Paragraph::new(self.text.to_owned())
             .centered()
@@ -71,16 +117,17 @@ impl Component for LoadingScreen {
         let size = rect.as_size();
         let pos = rect.as_position();
         let rect2 = Rect::new(pos.x + 2, pos.y + 2, size.width - 4, 2);
+        let rect3 = Rect::new(pos.x + 2, pos.y + 4, size.width - 4, 1);
 
         if self.show_gauge {
-            let percentage = self.percentage.unwrap_or_default();
             let gauge = LineGauge::default()
-                .block(Block::default().title(self.operation.as_str()))
-                .ratio(percentage)
-                .gauge_style(Style::default().fg(Color::Green))
+                .block(Block::default().title(self.progress.phase.description()))
+                .ratio(self.progress.fraction())
+                .gauge_style(theme.accent())
                 .line_set(symbols::line::ROUNDED);
 
             f.render_widget(gauge, rect2);
+            f.render_widget(Line::from(self.details()).style(theme.dimmed()), rect3);
         }
     }
 }
@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use ratatui::prelude::*;
+use ratatui::widgets::block::*;
+use ratatui::widgets::{Borders, List, ListItem, ListState};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::app::{NikaAction, Page};
+use crate::keymap::{Action, Context, Keymap};
+use crate::theme::Theme;
+use crate::traits::Component;
+use crate::{helpers, logging};
+
+/// Only the end of the file is shown, older lines are cut off.
+const TAIL_BYTES: u64 = 256 * 1024;
+
+/// How often the file is checked for new lines.
+const REFRESH: Duration = Duration::from_secs(1);
+
+/// Shows the end of the current log file, following it as it grows.
+pub struct LogsPage {
+    action_tx: Option<UnboundedSender<NikaAction>>,
+    /// Where the log files are.
+    dir: PathBuf,
+    /// The file shown, and its size when it was read.
+    file: Option<(PathBuf, u64)>,
+    lines: Vec<String>,
+    checked: Option<Instant>,
+    list_state: ListState,
+    /// Lines that fit on screen.
+    height: usize,
+}
+
+impl LogsPage {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            action_tx: None,
+            dir,
+            file: None,
+            lines: Vec::new(),
+            checked: None,
+            list_state: ListState::default(),
+            height: 0,
+        }
+    }
+
+    /// Reads the file again if it changed. The selection stays on the last line if it was there.
+    fn refresh(&mut self) {
+        if self.checked.is_some_and(|c| c.elapsed() < REFRESH) {
+            return;
+        }
+        self.checked = Some(Instant::now());
+
+        let file = logging::current_file(&self.dir).and_then(|path| {
+            let len = fs::metadata(&path).ok()?.len();
+            Some((path, len))
+        });
+        if file == self.file {
+            return;
+        }
+
+        let following = self
+            .list_state
+            .selected()
+            .is_none_or(|i| i + 1 >= self.lines.len());
+        self.lines = file
+            .as_ref()
+            .and_then(|(path, _)| logging::tail(path, TAIL_BYTES).ok())
+            .unwrap_or_default();
+        self.file = file;
+
+        if following {
+            self.list_state.select(self.lines.len().checked_sub(1));
+        }
+    }
+}
+
+impl Component for LogsPage {
+    fn init(&mut self, tx: UnboundedSender<NikaAction>) -> io::Result<()> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn context(&self) -> Context {
+        Context::Logs
+    }
+
+    fn handle_action(&mut self, action: Action) -> io::Result<Option<NikaAction>> {
+        let selected = self.list_state.selected().unwrap_or_default();
+        if let Some(index) = helpers::navigate(selected, self.lines.len(), self.height, action) {
+            self.list_state.select(Some(index));
+            return Ok(None);
+        }
+
+        match action {
+            Action::Quit => Ok(Some(NikaAction::Quit)),
+            Action::Home => Ok(Some(NikaAction::ChangePage(Page::Home))),
+            Action::Search => Ok(Some(NikaAction::ChangePage(Page::Search))),
+            _ => Ok(None),
+        }
+    }
+
+    fn update(&mut self, _action: NikaAction) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
+        self.refresh();
+
+        let title = match &self.file {
+            Some((path, _)) => format!("Logs ({})", path.display()),
+            None => String::from("Logs"),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border())
+            .border_type(BorderType::Rounded)
+            .title(title)
+            .title_alignment(Alignment::Center)
+            .title_bottom(keymap.hints(
+                Context::Logs,
+                &[(Action::Home, "for home"), (Action::Back, "back")],
+            ));
+
+        let items: Vec<ListItem> = self
+            .lines
+            .iter()
+            .map(|line| {
+                // e.g. "2026-10-18T09:12:44.061Z  WARN nika_tui::helpers: ..."
+                let style = match line.split_whitespace().nth(1) {
+                    Some("ERROR") => theme.error(),
+                    Some("WARN") => theme.warning(),
+                    Some("DEBUG" | "TRACE") => theme.dimmed(),
+                    _ => Style::new(),
+                };
+                ListItem::new(line.as_str()).style(style)
+            })
+            .collect();
+
+        let list = if items.is_empty() {
+            List::new([ListItem::new(format!(
+                "Nothing logged yet in {}. Set log_level in the options to log more.",
+                self.dir.display()
+            ))])
+        } else {
+            List::new(items)
+        };
+        let list = list.block(block).highlight_style(theme.highlight());
+
+        self.height = rect.height.saturating_sub(2) as usize;
+        f.render_stateful_widget(list, rect, &mut self.list_state);
+    }
+}
@@ -1,36 +1,94 @@
 use std::io;
 
-use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
 use ratatui::prelude::*;
 use ratatui::widgets::block::*;
-use ratatui::widgets::{Borders, Paragraph};
+use ratatui::widgets::{Borders, Clear, Paragraph, Wrap};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::app::{NikaAction, Page};
+use crate::keymap::{Action, Context, Keymap};
+use crate::theme::Theme;
 use crate::traits::Component;
 
 #[derive(Default)]
 pub struct HomePage {
     action_handler: Option<UnboundedSender<NikaAction>>,
+    /// Problems found in config.toml, shown in a dialog until a key is pressed.
+    config_errors: Vec<String>,
+}
+
+impl HomePage {
+    pub fn new(config_errors: Vec<String>) -> Self {
+        Self {
+            action_handler: None,
+            config_errors,
+        }
+    }
+
+    fn draw_config_errors(&self, f: &mut Frame<'_>, rect: Rect, theme: &Theme) {
+        let width = rect.width.saturating_sub(4).min(80);
+        let height = (self.config_errors.len() as u16 + 4).min(rect.height);
+        let area = Rect::new(
+            rect.x + (rect.width - width) / 2,
+            rect.y + (rect.height - height) / 2,
+            width,
+            height,
+        );
+
+        let block = Block::default()
+            .title(Span::styled("Errors in config.toml", theme.title()))
+            .title_alignment(Alignment::Center)
+            .title_bottom("Defaults are used instead. Press any key to continue.")
+            .border_style(theme.warning())
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+
+        let lines: Vec<Line> = self
+            .config_errors
+            .iter()
+            .map(|e| Line::from(format!("- {e}")))
+            .collect();
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block);
+
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
 }
 
 impl Component for HomePage {
-    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect) {
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
         let block = Block::default()
-            .title("Nika-tui".bold().light_red())
+            .title(Span::styled("Nika-tui", theme.title()))
             .title_alignment(Alignment::Center)
-            .border_style(Style::new().fg(Color::Red))
+            .border_style(theme.border())
             .borders(Borders::ALL)
             .title_bottom(
-                "<q> to quit, <s> for search, <o> for options, <m> for main page."
-                    .bold()
-                    .light_red(),
+                keymap
+                    .hints(
+                        Context::Home,
+                        &[
+                            (Action::Quit, "to quit"),
+                            (Action::Search, "for search"),
+                            (Action::Library, "for library"),
+                            (Action::Downloads, "for downloads"),
+                            (Action::Options, "for options"),
+                            (Action::Logs, "for logs"),
+                        ],
+                    )
+                    .set_style(theme.title()),
             );
 
-        let text = Text::from("Welcome to Nika!".light_red()).centered();
+        let text = Text::styled("Welcome to Nika!", theme.title()).centered();
         let paragraph = Paragraph::new(text).centered().block(block);
 
         f.render_widget(paragraph, rect);
+
+        if !self.config_errors.is_empty() {
+            self.draw_config_errors(f, rect, theme);
+        }
     }
 
     fn init(&mut self, tx: UnboundedSender<NikaAction>) -> std::io::Result<()> {
@@ -38,18 +96,39 @@ impl Component for HomePage {
         Ok(())
     }
 
-    fn handle_key_events(
-        &mut self,
-        key: crossterm::event::KeyEvent,
-    ) -> io::Result<Option<NikaAction>> {
-        match key.code {
-            KeyCode::Char('q') => Ok(Some(NikaAction::Quit)),
-            KeyCode::Char('s') => Ok(Some(NikaAction::ChangePage(Page::Search))),
+    fn context(&self) -> Context {
+        Context::Home
+    }
+
+    fn handle_action(&mut self, action: Action) -> io::Result<Option<NikaAction>> {
+        if !self.config_errors.is_empty() {
+            self.config_errors.clear();
+            return Ok(None);
+        }
+
+        match action {
+            Action::Quit => Ok(Some(NikaAction::Quit)),
+            Action::Search => Ok(Some(NikaAction::ChangePage(Page::Search))),
+            Action::Library => Ok(Some(NikaAction::ChangePage(Page::Library))),
+            Action::Downloads => Ok(Some(NikaAction::ChangePage(Page::Downloads))),
+            Action::Options => Ok(Some(NikaAction::ChangePage(Page::Options))),
+            Action::Logs => Ok(Some(NikaAction::ChangePage(Page::Logs))),
             _ => Ok(None),
         }
     }
 
-    fn update(&mut self, action: NikaAction) -> anyhow::Result<()> {
+    /// Unbound keys close the config errors too.
+    fn handle_key_events(&mut self, _key: KeyEvent) -> io::Result<Option<NikaAction>> {
+        self.config_errors.clear();
+        Ok(None)
+    }
+
+    fn back(&mut self) -> Option<NikaAction> {
+        self.config_errors.clear();
+        None
+    }
+
+    fn update(&mut self, _action: NikaAction) -> anyhow::Result<()> {
         Ok(())
     }
 }
@@ -1,5 +1,10 @@
 pub mod comic_page;
+pub mod downloads_page;
+pub mod library_page;
 pub mod loading_screen;
+pub mod logs_page;
 pub mod main_page;
+pub mod notifications;
 pub mod options_page;
+pub mod reader_page;
 pub mod search_page;
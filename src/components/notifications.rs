@@ -0,0 +1,385 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crossterm::event::KeyEvent;
+use ratatui::prelude::*;
+use ratatui::widgets::block::*;
+use ratatui::widgets::{Borders, Clear, Paragraph, Wrap};
+
+use crate::app::NikaAction;
+use crate::keymap::{Action, Context, Keymap};
+use crate::theme::Theme;
+
+/// How long a notification stays up, unless its details are open.
+const TIMEOUT: Duration = Duration::from_secs(6);
+
+/// Notifications shown at once. Older ones wait below the newest.
+const SHOWN: usize = 3;
+
+/// Something that failed, for the notification about it.
+#[derive(Clone)]
+pub struct ErrorReport {
+    /// What was being done, e.g. "Searching".
+    pub operation: String,
+    /// The source involved, if any.
+    pub source: Option<String>,
+    pub message: String,
+    /// Sent again to retry. Errors without one can't be retried.
+    pub retry: Option<Box<NikaAction>>,
+    /// The page it happened on, which a retry goes back to. The current one if not set.
+    pub page: Option<u64>,
+}
+
+impl ErrorReport {
+    /// `error` is shown with its causes.
+    pub fn new(operation: &str, error: impl fmt::Display) -> Self {
+        Self {
+            operation: operation.to_owned(),
+            source: None,
+            message: format!("{error:#}"),
+            retry: None,
+            page: None,
+        }
+    }
+
+    pub fn source(mut self, name: &str) -> Self {
+        self.source = Some(name.to_owned());
+        self
+    }
+
+    /// Makes the error retryable by sending `action` again to the page it happened on.
+    pub fn retry(mut self, action: NikaAction) -> Self {
+        self.retry = Some(Box::new(action));
+        self
+    }
+
+    pub fn on_page(mut self, page: u64) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    pub fn retryable(&self) -> bool {
+        self.retry.is_some()
+    }
+
+    /// e.g. "Searching failed (mangapill)".
+    fn title(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{} failed ({source})", self.operation),
+            None => format!("{} failed", self.operation),
+        }
+    }
+}
+
+/// Errors shown over whichever page is open, newest first. They go away on their own, unless
+/// their details are being read.
+#[derive(Default)]
+pub struct Notifications {
+    items: Vec<(ErrorReport, Instant)>,
+    /// Whether the newest one's details are open.
+    details: bool,
+}
+
+impl Notifications {
+    pub fn push(&mut self, report: ErrorReport) {
+        tracing::warn!(
+            operation = report.operation,
+            source = report.source,
+            retryable = report.retryable(),
+            "{}",
+            report.message
+        );
+        self.push_at(report, Instant::now());
+    }
+
+    fn push_at(&mut self, report: ErrorReport, now: Instant) {
+        self.items.push((report, now));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Drops the notifications that have been up long enough.
+    pub fn expire(&mut self, now: Instant) {
+        let keep = match self.details {
+            true => self.items.len().saturating_sub(1),
+            false => self.items.len(),
+        };
+        let mut index = 0;
+        self.items.retain(|(_, shown)| {
+            index += 1;
+            index > keep || now.duration_since(*shown) < TIMEOUT
+        });
+    }
+
+    /// Takes the keys meant for the notifications, which is every key while the details are
+    /// open. Returns `None` for the ones left to the page, and what to do for the others.
+    /// `is_open` tells whether a page is still open, for retries to go back to.
+    pub fn handle_key(
+        &mut self,
+        key: KeyEvent,
+        keymap: &Keymap,
+        is_open: impl Fn(u64) -> bool,
+    ) -> Option<NikaAction> {
+        let (report, _) = self.items.last_mut()?;
+
+        match (keymap.action(Context::Notification, key), self.details) {
+            (Some(Action::Retry), _) if report.retryable() => {
+                if report.page.is_some_and(|page| !is_open(page)) {
+                    // Kept, so it doesn't just vanish.
+                    report.retry = None;
+                    report
+                        .message
+                        .push_str(" (Its page was closed, so it can't be retried from here.)");
+                    return Some(NikaAction::Render);
+                }
+
+                self.details = false;
+                let (report, _) = self.items.pop()?;
+                let retry = report.retry?;
+                return Some(match report.page {
+                    Some(page) => NikaAction::Retry(page, retry),
+                    None => *retry,
+                });
+            }
+            (Some(Action::Details), false) => self.details = true,
+            (Some(Action::Dismiss), _) | (Some(Action::Back), true) => {
+                self.details = false;
+                self.items.pop();
+            }
+            (_, true) => {}
+            (_, false) => return None,
+        }
+
+        Some(NikaAction::Render)
+    }
+
+    pub fn draw(&self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
+        let hint = |action, text| {
+            keymap
+                .key(Context::Notification, action)
+                .map(|key| format!("<{key}> {text}"))
+        };
+
+        if self.details {
+            if let Some((report, _)) = self.items.last() {
+                let hints = [
+                    hint(Action::Back, "close"),
+                    report
+                        .retryable()
+                        .then(|| hint(Action::Retry, "retry"))
+                        .flatten(),
+                ];
+                self.draw_details(f, rect, theme, report, &hints);
+                return;
+            }
+        }
+
+        let width = rect.width.min(60);
+        if width < 3 {
+            return;
+        }
+        let mut bottom = rect.y + rect.height;
+
+        for (report, _) in self.items.iter().rev().take(SHOWN) {
+            let rows = report.message.chars().count().div_ceil(width as usize - 2);
+            let height = rows.clamp(1, 2) as u16 + 2;
+            if bottom < rect.y + height {
+                break;
+            }
+            bottom -= height;
+            let area = Rect::new(rect.x + rect.width - width, bottom, width, height);
+
+            let hints = [
+                hint(Action::Details, "details"),
+                report
+                    .retryable()
+                    .then(|| hint(Action::Retry, "retry"))
+                    .flatten(),
+                hint(Action::Dismiss, "dismiss"),
+            ];
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(theme.error())
+                .title(Span::styled(report.title(), theme.title()))
+                .title_bottom(hints.into_iter().flatten().collect::<Vec<_>>().join(", "));
+            let paragraph = Paragraph::new(report.message.as_str())
+                .wrap(Wrap { trim: true })
+                .block(block);
+
+            f.render_widget(Clear, area);
+            f.render_widget(paragraph, area);
+        }
+    }
+
+    fn draw_details(
+        &self,
+        f: &mut Frame<'_>,
+        rect: Rect,
+        theme: &Theme,
+        report: &ErrorReport,
+        hints: &[Option<String>],
+    ) {
+        let width = rect.width.saturating_sub(4).min(80);
+        let height = rect.height.saturating_sub(4).min(16);
+        let area = Rect::new(
+            rect.x + (rect.width - width) / 2,
+            rect.y + (rect.height - height) / 2,
+            width,
+            height,
+        );
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled("Operation: ", theme.dimmed()),
+                report.operation.as_str().into(),
+            ]),
+            Line::from(vec![
+                Span::styled("Source: ", theme.dimmed()),
+                report.source.as_deref().unwrap_or("none").into(),
+            ]),
+            Line::from(vec![
+                Span::styled("Retryable: ", theme.dimmed()),
+                if report.retryable() { "yes" } else { "no" }.into(),
+            ]),
+            Line::default(),
+        ];
+        lines.extend(report.message.lines().map(Line::from));
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(theme.error())
+            .title(Span::styled(report.title(), theme.title()))
+            .title_alignment(Alignment::Center)
+            .title_bottom(
+                hints
+                    .iter()
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block);
+
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{ErrorReport, Notifications, TIMEOUT};
+    use crate::app::NikaAction;
+    use crate::keymap::{KeyConfig, Keymap};
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn esc() -> KeyEvent {
+        KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn go_away_on_their_own_unless_read() {
+        let (keymap, _) = Keymap::new(&KeyConfig::default());
+        let start = Instant::now();
+        let mut notifications = Notifications::default();
+        notifications.push_at(ErrorReport::new("Searching", "timed out"), start);
+        notifications.push_at(ErrorReport::new("Loading", "404"), start);
+
+        notifications.handle_key(ctrl('e'), &keymap, |_| true);
+        notifications.expire(start + TIMEOUT + Duration::from_secs(1));
+
+        // The one being read stays.
+        assert_eq!(notifications.items.len(), 1);
+        assert_eq!(notifications.items[0].0.operation, "Loading");
+
+        notifications.handle_key(esc(), &keymap, |_| true);
+        assert!(notifications.is_empty());
+    }
+
+    #[test]
+    fn keys_reach_the_page_unless_the_details_are_open() {
+        let (keymap, _) = Keymap::new(&KeyConfig::default());
+        let mut notifications = Notifications::default();
+
+        assert!(notifications.handle_key(esc(), &keymap, |_| true).is_none());
+
+        notifications.push(ErrorReport::new("Searching", "timed out"));
+        assert!(notifications.handle_key(esc(), &keymap, |_| true).is_none());
+        let q = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+        assert!(notifications.handle_key(q, &keymap, |_| true).is_none());
+
+        notifications.handle_key(ctrl('e'), &keymap, |_| true);
+        assert!(matches!(
+            notifications.handle_key(q, &keymap, |_| true),
+            Some(NikaAction::Render)
+        ));
+        assert!(!notifications.is_empty());
+
+        notifications.handle_key(ctrl('x'), &keymap, |_| true);
+        assert!(notifications.is_empty());
+    }
+
+    #[test]
+    fn retrying_sends_the_action_again() {
+        let (keymap, _) = Keymap::new(&KeyConfig::default());
+        let mut notifications = Notifications::default();
+        let report = ErrorReport::new("Searching", "timed out")
+            .source("mangapill")
+            .retry(NikaAction::SearchComic("berserk".into()));
+
+        assert!(report.retryable());
+        assert_eq!(report.title(), "Searching failed (mangapill)");
+        notifications.push(report);
+
+        let action = notifications.handle_key(ctrl('r'), &keymap, |_| true);
+        assert!(matches!(action, Some(NikaAction::SearchComic(q)) if q == "berserk"));
+        assert!(notifications.is_empty());
+    }
+
+    #[test]
+    fn retries_go_back_to_their_page_while_it_is_open() {
+        let (keymap, _) = Keymap::new(&KeyConfig::default());
+        let mut notifications = Notifications::default();
+        let report = ErrorReport::new("Searching", "timed out")
+            .retry(NikaAction::SearchComic("berserk".into()))
+            .on_page(3);
+
+        notifications.push(report.clone());
+        let action = notifications.handle_key(ctrl('r'), &keymap, |page| page == 3);
+        assert!(
+            matches!(action, Some(NikaAction::Retry(3, a)) if matches!(*a, NikaAction::SearchComic(_)))
+        );
+
+        // Its page is gone, so it stays to say so.
+        notifications.push(report);
+        notifications.handle_key(ctrl('r'), &keymap, |_| false);
+        assert_eq!(notifications.items.len(), 1);
+        assert!(!notifications.items[0].0.retryable());
+        assert!(
+            notifications.items[0]
+                .0
+                .message
+                .contains("can't be retried")
+        );
+
+        // Nothing to retry, so the key is the page's.
+        assert!(
+            notifications
+                .handle_key(ctrl('r'), &keymap, |_| true)
+                .is_none()
+        );
+        assert_eq!(notifications.items.len(), 1);
+    }
+}
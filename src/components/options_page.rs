@@ -1,25 +1,243 @@
+use std::io;
+
+use crossterm::event;
 use ratatui::prelude::*;
 use ratatui::widgets::block::*;
-use ratatui::widgets::{Borders, Paragraph};
-#[derive(Debug, Default)]
-pub struct OptionsPage;
+use ratatui::widgets::{Borders, List, ListItem, ListState, Paragraph};
+use tokio::sync::mpsc::UnboundedSender;
+use tui_textarea::TextArea;
+
+use crate::app::{NikaAction, Page};
+use crate::config::{Config, FIELDS, Field, FieldKind};
+use crate::helpers;
+use crate::keymap::{Action, Context, Keymap};
+use crate::theme::Theme;
+use crate::traits::Component;
+
+/// Lists the settings and edits them in place. Changes are saved and applied right away.
+pub struct OptionsPage {
+    action_tx: Option<UnboundedSender<NikaAction>>,
+    config: Config,
+    list_state: ListState,
+    /// Holds the value being typed, if any.
+    editor: Option<TextArea<'static>>,
+    /// Why the last change was rejected, or that it was saved.
+    message: Option<Result<String, String>>,
+    /// Settings that fit on screen.
+    height: usize,
+}
 
 impl OptionsPage {
-    pub fn render_page(area: Rect, frame: &mut Frame)
-    where
-        Self: Sized,
-    {
+    pub fn new(config: Config) -> Self {
+        Self {
+            action_tx: None,
+            config,
+            list_state: ListState::default().with_selected(Some(0)),
+            editor: None,
+            message: None,
+            height: 0,
+        }
+    }
+
+    fn selected(&self) -> &'static Field {
+        &FIELDS[self.list_state.selected().unwrap_or_default()]
+    }
+
+    /// Bools flip and choices cycle, the rest are typed in.
+    fn edit(&mut self) {
+        let field = self.selected();
+        let value = self.config.get(field.key);
+
+        match field.kind {
+            FieldKind::Bool => {
+                self.apply(field, &(value != "true").to_string());
+            }
+            FieldKind::Choice(choices) => {
+                let index = choices.iter().position(|c| *c == value);
+                let next = index.map_or(0, |i| (i + 1) % choices.len());
+                self.apply(field, choices[next]);
+            }
+            _ => {
+                let mut editor = TextArea::new(vec![value]);
+                editor.move_cursor(tui_textarea::CursorMove::End);
+                self.editor = Some(editor);
+            }
+        }
+    }
+
+    /// Validates and saves a new value. Returns false if it was rejected.
+    fn apply(&mut self, field: &Field, input: &str) -> bool {
+        let mut config = self.config.clone();
+
+        if let Err(e) = config.set(field.key, input) {
+            self.message = Some(Err(format!("Invalid {}: {e}", field.key)));
+            return false;
+        }
+
+        self.message = Some(match config.save() {
+            Ok(true) => Ok(format!("Saved {}", field.key)),
+            Ok(false) => Ok(format!("{} is unchanged", field.key)),
+            Err(e) => Err(format!("Couldn't save the config: {e}")),
+        });
+
+        self.config = config.clone();
+        if let Some(tx) = &self.action_tx {
+            let _ = tx.send(NikaAction::ConfigChanged(config));
+        }
+        true
+    }
+}
+
+impl Component for OptionsPage {
+    fn init(&mut self, tx: UnboundedSender<NikaAction>) -> io::Result<()> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn context(&self) -> Context {
+        match self.editor {
+            Some(_) => Context::Editing,
+            None => Context::Options,
+        }
+    }
+
+    fn handle_action(&mut self, action: Action) -> io::Result<Option<NikaAction>> {
+        if let Some(editor) = &self.editor {
+            if action == Action::Confirm {
+                let input = editor.lines()[0].clone();
+                if self.apply(self.selected(), &input) {
+                    self.editor = None;
+                }
+            }
+            return Ok(None);
+        }
+
+        let selected = self.list_state.selected().unwrap_or_default();
+        if let Some(index) = helpers::navigate(selected, FIELDS.len(), self.height, action) {
+            self.list_state.select(Some(index));
+            self.message = None;
+            return Ok(None);
+        }
+
+        match action {
+            Action::Quit => Ok(Some(NikaAction::Quit)),
+            Action::Home => Ok(Some(NikaAction::ChangePage(Page::Home))),
+            Action::Select => {
+                self.edit();
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Types into the editor.
+    fn handle_key_events(&mut self, key: event::KeyEvent) -> io::Result<Option<NikaAction>> {
+        if let Some(editor) = &mut self.editor {
+            editor.input(key);
+        }
+        Ok(None)
+    }
+
+    /// Stops editing without changing anything.
+    fn back(&mut self) -> Option<NikaAction> {
+        match self.editor.take() {
+            Some(_) => {
+                self.message = None;
+                None
+            }
+            None => Some(NikaAction::Back),
+        }
+    }
+
+    fn update(&mut self, action: NikaAction) -> anyhow::Result<()> {
+        if let NikaAction::ConfigChanged(config) = action {
+            self.config = config;
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Fill(1), Constraint::Length(4)])
+            .split(rect);
+
         let block = Block::default()
-            .title("Options".bold().light_red())
+            .borders(Borders::ALL)
+            .border_style(theme.border())
+            .border_type(BorderType::Rounded)
+            .title("Options")
             .title_alignment(Alignment::Center)
+            .title_bottom(keymap.hints(
+                Context::Options,
+                &[
+                    (Action::Select, "to edit, toggle or cycle"),
+                    (Action::Back, "back"),
+                    (Action::Home, "for home"),
+                ],
+            ));
+
+        let width = FIELDS.iter().map(|f| f.key.len()).max().unwrap_or_default();
+        let items = FIELDS
+            .iter()
+            .map(|field| {
+                let value = self.config.get(field.key);
+                let value = match (field.kind, value.is_empty()) {
+                    (FieldKind::Path, true) => "(default)".to_owned(),
+                    _ => value,
+                };
+
+                ListItem::new(Line::from(vec![
+                    format!("{:width$}  ", field.key).into(),
+                    Span::styled(format!("{:8}", field.kind.name()), theme.dimmed()),
+                    value.into(),
+                ]))
+            })
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(theme.highlight());
+
+        self.height = layout[0].height.saturating_sub(2) as usize;
+        f.render_stateful_widget(list, layout[0], &mut self.list_state);
+
+        let field = self.selected();
+        let help = Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::new().on_dark_gray());
+            .border_type(BorderType::Rounded)
+            .title(field.key);
 
-        let text = Paragraph::new("Not implemented")
-            .centered()
-            .bold()
-            .block(block);
+        match &mut self.editor {
+            Some(editor) => {
+                editor.set_block(
+                    help.title_bottom(
+                        keymap
+                            .hints(
+                                Context::Editing,
+                                &[(Action::Confirm, "to save"), (Action::Back, "to cancel")],
+                            )
+                            .set_style(theme.warning()),
+                    ),
+                );
+                f.render_widget(editor.widget(), layout[1]);
+            }
+            None => {
+                let mut lines = vec![Line::from(field.description)];
+                if let FieldKind::Choice(choices) = field.kind {
+                    lines[0].spans.push(Span::styled(
+                        format!(" ({})", choices.join(", ")),
+                        theme.dimmed(),
+                    ));
+                }
+                match &self.message {
+                    Some(Ok(message)) => lines.push(Line::styled(message, theme.accent())),
+                    Some(Err(message)) => lines.push(Line::styled(message, theme.error())),
+                    None => {}
+                }
 
-        frame.render_widget(text, area)
+                f.render_widget(Paragraph::new(lines).block(help), layout[1]);
+            }
+        }
     }
 }
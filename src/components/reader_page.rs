@@ -0,0 +1,329 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use image::DynamicImage;
+use ratatui::prelude::*;
+use ratatui::widgets::block::*;
+use ratatui::widgets::{Borders, Paragraph, Wrap};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::app::{NikaAction, Page};
+use crate::components::comic_page::open_chapter;
+use crate::config::Config;
+use crate::graphics::{self, Fit, GraphicsProtocol, ImageWidget, Rendered};
+use crate::helpers::{self, Shared};
+use crate::history::ReadingHistory;
+use crate::keymap::{Action, Context, Keymap};
+use crate::models::comic::{Chapter, Comic, ComicInfo};
+use crate::theme::Theme;
+use crate::traits::{Component, Source};
+use crate::viewer;
+
+/// What the cached image was rendered for.
+#[derive(PartialEq)]
+struct RenderKey {
+    page: usize,
+    area: Rect,
+    fit: Fit,
+    scroll: u16,
+}
+
+/// Shows the pages of a downloaded chapter inside the terminal.
+pub struct ReaderPage {
+    action_tx: Option<UnboundedSender<NikaAction>>,
+    comic: Comic,
+    source: Arc<dyn Source>,
+    info: ComicInfo,
+    chapter: Chapter,
+    pages: Vec<PathBuf>,
+    page: usize,
+    image: Option<DynamicImage>,
+    error: Option<String>,
+    /// Shown above the page, e.g. when some pages failed to download.
+    message: Option<String>,
+    fit: Fit,
+    /// Rows scrolled down, when the page is taller than the screen.
+    scroll: u16,
+    more_below: bool,
+    protocol: GraphicsProtocol,
+    /// Cached image, with the number of columns and rows it covers.
+    rendered: Option<(RenderKey, Rendered, u16, u16)>,
+    history: Shared<ReadingHistory>,
+    config: Config,
+}
+
+impl ReaderPage {
+    pub fn new(
+        comic: Comic,
+        source: Arc<dyn Source>,
+        info: ComicInfo,
+        chapter: Chapter,
+        path: &str,
+        config: Config,
+        history: Shared<ReadingHistory>,
+    ) -> Self {
+        let (pages, error) = match viewer::pages(path.as_ref()) {
+            Ok(pages) => (pages, None),
+            Err(e) => (Vec::new(), Some(format!("Couldn't read {path}: {e}"))),
+        };
+
+        let mut reader = Self {
+            action_tx: None,
+            comic,
+            source,
+            info,
+            chapter,
+            pages,
+            page: 0,
+            image: None,
+            error,
+            message: None,
+            fit: Fit::default(),
+            scroll: 0,
+            more_below: false,
+            protocol: config.reader().graphics.detect(),
+            rendered: None,
+            history,
+            config,
+        };
+
+        reader.go_to(0);
+        reader
+    }
+
+    /// Decodes the given page and records it as the last one viewed.
+    fn go_to(&mut self, page: usize) {
+        let Some(path) = self.pages.get(page) else {
+            return;
+        };
+
+        self.page = page;
+        self.scroll = 0;
+        self.rendered = None;
+
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        if !helpers::can_decode(&extension) {
+            self.image = None;
+            self.error = Some(format!(
+                "Page {} is {}, which the built-in reader can't show. Open the chapter in the \
+                 external viewer instead.",
+                page + 1,
+                extension.to_uppercase()
+            ));
+        } else {
+            match image::open(path) {
+                Ok(image) => {
+                    self.image = Some(image);
+                    self.error = None;
+                }
+                Err(e) => {
+                    self.image = None;
+                    self.error = Some(format!("Couldn't open {}: {e}", path.display()));
+                }
+            }
+        }
+
+        let mut history = self.history.lock();
+        history.set_last_page(
+            self.source.name(),
+            &self.comic.source,
+            &self.chapter,
+            page + 1,
+        );
+        // Losing the page number isn't worth interrupting the reader for.
+        let _ = history.save();
+    }
+
+    /// The chapter after this one. Sources list chapters newest first.
+    fn next_chapter(&self) -> Option<&Chapter> {
+        let index = self
+            .comic
+            .chapters
+            .iter()
+            .position(|c| c.source == self.chapter.source)?;
+
+        index.checked_sub(1).map(|i| &self.comic.chapters[i])
+    }
+}
+
+impl Component for ReaderPage {
+    fn init(&mut self, tx: UnboundedSender<NikaAction>) -> io::Result<()> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn context(&self) -> Context {
+        Context::Reader
+    }
+
+    fn handle_action(&mut self, action: Action) -> io::Result<Option<NikaAction>> {
+        match action {
+            Action::Quit => Ok(Some(NikaAction::Quit)),
+            Action::Home => Ok(Some(NikaAction::ChangePage(Page::Home))),
+            Action::Search => Ok(Some(NikaAction::ChangePage(Page::Search))),
+
+            Action::NextPage => {
+                self.go_to(self.page + 1);
+                Ok(None)
+            }
+            Action::PreviousPage => {
+                self.go_to(self.page.saturating_sub(1));
+                Ok(None)
+            }
+            Action::Top => {
+                self.go_to(0);
+                Ok(None)
+            }
+            Action::Bottom => {
+                self.go_to(self.pages.len().saturating_sub(1));
+                Ok(None)
+            }
+
+            Action::Down => {
+                if self.more_below {
+                    self.scroll += 1;
+                }
+                Ok(None)
+            }
+            Action::Up => {
+                self.scroll = self.scroll.saturating_sub(1);
+                Ok(None)
+            }
+
+            Action::FitWidth => {
+                self.fit = Fit::Width;
+                Ok(None)
+            }
+            Action::FitHeight => {
+                self.fit = Fit::Height;
+                self.scroll = 0;
+                Ok(None)
+            }
+
+            Action::NextChapter => Ok(self
+                .next_chapter()
+                .map(|c| NikaAction::FetchChapter(c.to_owned()))),
+
+            _ => Ok(None),
+        }
+    }
+
+    fn update(&mut self, action: NikaAction) -> anyhow::Result<()> {
+        match action {
+            NikaAction::FetchChapter(chapter) => {
+                // The next chapter takes this one's place, instead of piling up on top of it.
+                self.action_tx.as_ref().unwrap().send(NikaAction::Back)?;
+                open_chapter(
+                    self.action_tx.clone().unwrap(),
+                    self.source.clone(),
+                    self.comic.clone(),
+                    self.info.clone(),
+                    chapter.clone(),
+                    &self.config,
+                );
+
+                let mut history = self.history.lock();
+                history.mark_read(self.source.name(), &self.comic.source, &chapter);
+                // Like the page number, not worth stopping the next chapter for.
+                let _ = history.save();
+            }
+            NikaAction::ShowMessage(message) => self.message = Some(message),
+            NikaAction::ConfigChanged(config) => {
+                self.protocol = config.reader().graphics.detect();
+                self.rendered = None;
+                self.config = config;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
+        let fit = match self.fit {
+            Fit::Height => "fit height",
+            Fit::Width => "fit width",
+        };
+        let title = format!(
+            "{} - {} (page {} of {}, {fit})",
+            self.comic.name,
+            self.chapter.name,
+            self.page + 1,
+            self.pages.len()
+        );
+
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(theme.border())
+            .title(title)
+            .title_alignment(Alignment::Center)
+            .title_bottom(keymap.hints(
+                Context::Reader,
+                &[
+                    (Action::PreviousPage, "previous"),
+                    (Action::NextPage, "next"),
+                    (Action::Up, "scroll up"),
+                    (Action::Down, "scroll down"),
+                    (Action::FitWidth, "fit width"),
+                    (Action::FitHeight, "fit height"),
+                    (Action::NextChapter, "next chapter"),
+                    (Action::Back, "back"),
+                ],
+            ));
+
+        if let Some(message) = &self.message {
+            let message =
+                Title::from(message.as_str().set_style(theme.error())).alignment(Alignment::Left);
+            block = block.title(message);
+        }
+
+        let area = block.inner(rect);
+        f.render_widget(block, rect);
+
+        let Some(image) = &self.image else {
+            let message = self
+                .error
+                .as_deref()
+                .unwrap_or("This chapter has no pages.");
+            let p = Paragraph::new(message).centered().wrap(Wrap { trim: true });
+            f.render_widget(p, area);
+            return;
+        };
+
+        let (cell_w, cell_h) = self.protocol.cell_size();
+        let key = RenderKey {
+            page: self.page,
+            area,
+            fit: self.fit,
+            scroll: self.scroll,
+        };
+
+        if self.rendered.as_ref().map(|(k, ..)| k) != Some(&key) {
+            let (visible, more_below) = graphics::fit(
+                image,
+                area.width as u32 * cell_w,
+                area.height as u32 * cell_h,
+                self.fit,
+                self.scroll as u32 * cell_h,
+            );
+
+            let cols = visible.width().div_ceil(cell_w) as u16;
+            let rows = visible.height().div_ceil(cell_h) as u16;
+
+            self.more_below = more_below;
+            let rendered = Rendered::encode(self.protocol, &visible, cols, rows);
+            self.rendered = Some((key, rendered, cols, rows));
+        }
+
+        if let Some((_, rendered, cols, rows)) = &self.rendered {
+            // Centers the page horizontally.
+            let x = area.x + area.width.saturating_sub(*cols) / 2;
+            let image_area =
+                Rect::new(x, area.y, (*cols).min(area.width), (*rows).min(area.height));
+
+            f.render_widget(ImageWidget::new(rendered), image_area);
+        }
+    }
+}
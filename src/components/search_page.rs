@@ -1,115 +1,145 @@
 use std::io;
 use std::sync::Arc;
 
-use crossterm::event::{self, KeyCode, KeyEventKind};
+use crossterm::event::{self, KeyEventKind};
 use ratatui::prelude::*;
 use ratatui::widgets::block::*;
-use ratatui::widgets::{Borders, List, ListDirection, ListItem, ListState};
+use ratatui::widgets::{Borders, List, ListItem, ListState};
 use tokio::sync::mpsc::UnboundedSender;
 use tui_textarea::TextArea;
 
 use crate::app::{InputMode, NikaAction, Page};
+use crate::components::notifications::ErrorReport;
 use crate::helpers;
+use crate::keymap::{Action, Context, Keymap};
 use crate::models::comic::Comic;
-use crate::models::sources::mangapill::MangapillSource;
+use crate::models::sources::registry::{Capability, SourceRegistry};
+use crate::operation::Operation;
+use crate::theme::Theme;
 use crate::traits::{Component, Source};
 
-#[derive(Default)]
 pub struct SearchPage {
     action_tx: Option<UnboundedSender<NikaAction>>,
     search_results: Vec<Comic>,
     text_area: TextArea<'static>,
     mode: InputMode,
     list_state: ListState,
-    sources: Vec<Arc<dyn Source>>,
+    sources: SourceRegistry,
     selected_source_index: usize,
+    /// Results that fit on screen.
+    height: usize,
+}
+
+impl SearchPage {
+    pub fn new(sources: SourceRegistry) -> Self {
+        Self {
+            action_tx: None,
+            search_results: Vec::new(),
+            text_area: TextArea::default(),
+            mode: InputMode::default(),
+            list_state: ListState::default(),
+            sources,
+            selected_source_index: 0,
+            height: 0,
+        }
+    }
+
+    /// The selected one of the sources that can search, if any are enabled.
+    fn source(&self) -> Option<Arc<dyn Source>> {
+        let sources = self.sources.enabled(Capability::Search);
+        match sources.len() {
+            0 => None,
+            len => Some(sources[self.selected_source_index % len].clone()),
+        }
+    }
+
+    fn no_source(&self) -> anyhow::Result<()> {
+        let sender = self.action_tx.as_ref().unwrap();
+        sender.send(NikaAction::Error(ErrorReport::new(
+            "Searching",
+            "No source can search, enable one in the options",
+        )))?;
+        Ok(())
+    }
 }
 
 impl Component for SearchPage {
     fn init(&mut self, tx: UnboundedSender<NikaAction>) -> io::Result<()> {
         self.action_tx = Some(tx);
-        let mut vec: Vec<Arc<dyn Source>> = vec![
-            Arc::new(MangapillSource::new()),
-            // Arc::new(MangaseeSource::new()),
-        ];
-        self.sources.append(&mut vec);
-
         Ok(())
     }
 
-    fn handle_key_events(&mut self, key: event::KeyEvent) -> io::Result<Option<NikaAction>> {
+    fn context(&self) -> Context {
         match self.mode {
-            InputMode::Normal => {
-                match key.code {
-                    KeyCode::Char('h') => Ok(Some(NikaAction::ChangePage(Page::Home))),
-                    KeyCode::Char('q') => Ok(Some(NikaAction::Quit)),
-                    KeyCode::Char('/') => {
-                        self.mode = InputMode::Editing;
-                        self.list_state.select(None);
-                        Ok(None)
-                    }
-
-                    KeyCode::Char('s') => {
-                        self.selected_source_index += 1;
-
-                        if self.selected_source_index == self.sources.len() {
-                            self.selected_source_index = 0;
-                        }
-                        Ok(None)
-                    }
-
-                    KeyCode::Up => {
-                        let selected = self.list_state.selected().unwrap_or_default();
-                        let index = helpers::get_new_selection_index(
-                            selected,
-                            self.search_results.len(),
-                            ListDirection::BottomToTop,
-                        );
-
-                        self.list_state.select(Some(index));
-
-                        Ok(None) // temporary
-                    }
-
-                    KeyCode::Down => {
-                        let selected = self.list_state.selected().unwrap_or_default();
-                        let index = helpers::get_new_selection_index(
-                            selected,
-                            self.search_results.len(),
-                            ListDirection::TopToBottom,
-                        );
-
-                        self.list_state.select(Some(index));
-
-                        Ok(None) // temporary
-                    }
-
-                    KeyCode::Enter => {
-                        let comic = &self.search_results[self.list_state.selected().unwrap()];
-                        Ok(Some(NikaAction::SelectComic(comic.to_owned())))
-                    }
-
-                    _ => Ok(None),
-                }
+            InputMode::Normal => Context::Search,
+            InputMode::Editing => Context::Editing,
+        }
+    }
+
+    fn handle_action(&mut self, action: Action) -> io::Result<Option<NikaAction>> {
+        if let InputMode::Editing = self.mode {
+            if action == Action::Confirm {
+                self.mode = InputMode::Normal;
+                self.list_state.select(Some(0));
             }
-            InputMode::Editing => {
-                if let KeyEventKind::Press = key.kind {
-                    match key.code {
-                        KeyCode::Enter => {
-                            self.mode = InputMode::Normal;
-                            self.list_state.select(Some(0));
-                            return Ok(None);
-                        }
+            return Ok(None);
+        }
 
-                        _ => {
-                            self.text_area.input(key);
-                            let query = &self.text_area.lines()[0];
-                            return Ok(Some(NikaAction::SearchComic(query.to_owned())));
-                        }
-                    }
-                }
+        let selected = self.list_state.selected().unwrap_or_default();
+        let len = self.search_results.len();
+        if let Some(index) = helpers::navigate(selected, len, self.height, action) {
+            self.list_state.select(Some(index));
+            return Ok(None);
+        }
+
+        match action {
+            Action::Home => Ok(Some(NikaAction::ChangePage(Page::Home))),
+            Action::Quit => Ok(Some(NikaAction::Quit)),
+            // To see why a search came back empty.
+            Action::Logs => Ok(Some(NikaAction::ChangePage(Page::Logs))),
+            Action::Edit => {
+                self.mode = InputMode::Editing;
+                self.list_state.select(None);
                 Ok(None)
             }
+
+            Action::CycleSource => {
+                let len = self.sources.enabled(Capability::Search).len();
+                self.selected_source_index = (self.selected_source_index + 1) % len.max(1);
+                Ok(None)
+            }
+
+            Action::Select => {
+                let comic = self
+                    .list_state
+                    .selected()
+                    .and_then(|i| self.search_results.get(i));
+                Ok(comic.map(|c| NikaAction::SelectComic(c.to_owned())))
+            }
+
+            _ => Ok(None),
+        }
+    }
+
+    /// Types into the search box.
+    fn handle_key_events(&mut self, key: event::KeyEvent) -> io::Result<Option<NikaAction>> {
+        if let (InputMode::Editing, KeyEventKind::Press) = (&self.mode, key.kind) {
+            self.text_area.input(key);
+            let query = &self.text_area.lines()[0];
+            return Ok(Some(NikaAction::SearchComic(query.to_owned())));
+        }
+        Ok(None)
+    }
+
+    /// Stops editing first, like Enter.
+    fn back(&mut self) -> Option<NikaAction> {
+        match self.mode {
+            InputMode::Editing => {
+                self.mode = InputMode::Normal;
+                self.list_state.select(Some(0));
+                None
+            }
+            InputMode::Normal => Some(NikaAction::Back),
         }
     }
 
@@ -117,14 +147,20 @@ impl Component for SearchPage {
         match action {
             NikaAction::SearchComic(query) => {
                 let sender = self.action_tx.clone().unwrap();
-                let s = self.sources[self.selected_source_index].clone();
+                let Some(s) = self.source() else {
+                    return self.no_source();
+                };
 
                 tokio::spawn(async move {
                     let results = s.search(&query).await;
 
                     let message = match results {
                         Ok(val) => NikaAction::SetSearchResults(val),
-                        Err(_) => NikaAction::Error,
+                        Err(e) => NikaAction::Error(
+                            ErrorReport::new("Searching", e)
+                                .source(s.name())
+                                .retry(NikaAction::SearchComic(query)),
+                        ),
                     };
                     sender.send(message).unwrap();
                 });
@@ -134,26 +170,55 @@ impl Component for SearchPage {
 
             NikaAction::SelectComic(mut c) => {
                 let sender = self.action_tx.as_ref().unwrap().to_owned();
-                sender.send(NikaAction::ChangePage(Page::LoadingScreen(
-                    "Loading Comics...",
-                    None,
-                    false
-                )))?;
-                let source = self.sources[self.selected_source_index].clone();
-
-                tokio::spawn(async move {
-                    let chapters = source.get_chapters(&c).await.unwrap();
-                    let info = source.get_info(&c).await.unwrap();
-
-                    c.chapters = chapters;
-
+                // Not the selected source, which may have changed since the search.
+                let Some(source) = self.sources.for_url(&c.source) else {
+                    sender.send(NikaAction::Error(ErrorReport::new(
+                        "Loading the comic",
+                        format!("No enabled source has {}", c.source),
+                    )))?;
+                    return Ok(());
+                };
+
+                Operation::spawn(|operation| async move {
                     sender
-                        .send(NikaAction::ChangePage(Page::Comic(
-                            c,
-                            source,
-                            info.unwrap(),
+                        .send(NikaAction::ChangePage(Page::LoadingScreen(
+                            "Loading Comics...",
+                            false,
+                            operation.clone(),
                         )))
                         .unwrap();
+
+                    let loaded = async {
+                        let chapters = source.get_chapters(&c).await?;
+                        let info = source.get_info(&c).await?;
+                        anyhow::Ok((chapters, info))
+                    };
+
+                    let action = match loaded.await {
+                        Ok((chapters, Some(info))) => {
+                            c.chapters = chapters;
+                            let page = Page::Comic(c, source, info);
+                            NikaAction::LeaveLoadingScreen(operation, Some(page))
+                        }
+                        result => {
+                            let error = match result {
+                                Err(e) => e,
+                                _ => anyhow::anyhow!(
+                                    "{} has no info about {}",
+                                    source.name(),
+                                    c.name
+                                ),
+                            };
+                            let leave = NikaAction::LeaveLoadingScreen(operation, None);
+                            sender.send(leave).unwrap();
+                            NikaAction::Error(
+                                ErrorReport::new("Loading the comic", error)
+                                    .source(source.name())
+                                    .retry(NikaAction::SelectComic(c)),
+                            )
+                        }
+                    };
+                    sender.send(action).unwrap();
                 });
             }
             _ => {}
@@ -162,10 +227,11 @@ impl Component for SearchPage {
         Ok(())
     }
 
-    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect) {
-        let (scolor, rcolor) = match self.mode {
-            InputMode::Normal => (Color::default(), Color::Yellow),
-            InputMode::Editing => (Color::Yellow, Color::default()),
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap) {
+        // The box being typed into or navigated stands out.
+        let (search_style, results_style) = match self.mode {
+            InputMode::Normal => (theme.border(), theme.accent()),
+            InputMode::Editing => (theme.accent(), theme.border()),
         };
 
         let layout = Layout::default()
@@ -175,7 +241,7 @@ impl Component for SearchPage {
 
         let block1 = Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::new().fg(scolor))
+            .border_style(search_style)
             .border_type(BorderType::Rounded)
             .title("Search")
             .title_alignment(Alignment::Center);
@@ -184,11 +250,22 @@ impl Component for SearchPage {
 
         let block2 = Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::new().fg(rcolor))
+            .border_style(results_style)
             .border_type(BorderType::Rounded)
             .title("Results")
             .title_alignment(Alignment::Center)
-            .title_bottom("</> to edit and <Enter> to stop editing");
+            .title_bottom(format!(
+                "{}, {}",
+                keymap.hints(Context::Editing, &[(Action::Confirm, "to stop editing")]),
+                keymap.hints(
+                    Context::Search,
+                    &[
+                        (Action::Edit, "to edit"),
+                        (Action::CycleSource, "next source"),
+                        (Action::Logs, "for logs"),
+                    ]
+                ),
+            ));
 
         let items = self
             .search_results
@@ -196,18 +273,20 @@ impl Component for SearchPage {
             .map(|f| ListItem::new(f.name.as_str()))
             .collect::<Vec<ListItem>>();
 
+        let source = self.source();
         let source = Text::from(format!(
             "Source: {}",
-            self.sources[self.selected_source_index].name()
+            source.as_ref().map_or("none", |s| s.name())
         ))
         .centered();
 
         let results = List::new(items)
             .block(block2)
-            .highlight_style(Style::new().fg(Color::Yellow));
+            .highlight_style(theme.highlight());
 
         f.render_widget(self.text_area.widget(), layout[0]);
         f.render_widget(source, layout[0]);
+        self.height = layout[1].height.saturating_sub(2) as usize;
         f.render_stateful_widget(results, layout[1], &mut self.list_state);
     }
 }
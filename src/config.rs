@@ -1,48 +1,301 @@
-use std::{env, fs};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
 
 use serde::{Deserialize, Serialize};
+use toml_edit::DocumentMut;
 
-use crate::constants::DEFAULT_CONFIG_DIR;
+use crate::constants::DOWNLOAD_DIR;
+use crate::downloads::DownloadOptions;
+use crate::graphics::GraphicsProtocol;
+use crate::keymap::{KeyConfig, Keymap};
+use crate::logging::LogLevel;
+use crate::models::sources::registry::SourcesConfig;
+use crate::theme::{Theme, ThemeConfig};
+use crate::viewer::ViewerConfig;
+use crate::{helpers, paths};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
+    #[serde(default)]
     anilist_token: String,
+    #[serde(default = "default_chapter_page_size")]
     chapter_page_size: usize,
+    #[serde(default)]
+    viewer: ViewerConfig,
+    #[serde(default)]
+    reader: ReaderConfig,
+    /// How many chapters the download queue fetches at once.
+    #[serde(default = "default_download_concurrency")]
+    download_concurrency: usize,
+    /// Where chapters are saved. Defaults to a directory under ~/.local/share.
+    #[serde(default)]
+    download_dir: Option<PathBuf>,
+    /// Also pack downloaded chapters into CBZ archives with a ComicInfo.xml.
+    #[serde(default)]
+    export_cbz: bool,
+    /// The preset and the bindings that replace its keys.
+    #[serde(default)]
+    keys: KeyConfig,
+    /// Which colors to draw with.
+    #[serde(default)]
+    theme: ThemeConfig,
+    /// How much goes into the log file, unless NIKA_LOG says otherwise.
+    #[serde(default)]
+    log_level: LogLevel,
+    /// Which sources are used.
+    #[serde(default)]
+    sources: SourcesConfig,
+}
+
+fn default_chapter_page_size() -> usize {
+    25
+}
+
+fn default_download_concurrency() -> usize {
+    2
+}
+
+/// What a config value holds, and how it's typed in on the options page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    /// A whole number, at least `min`.
+    Number {
+        min: u64,
+    },
+    Text,
+    Bool,
+    /// One of the given words.
+    Choice(&'static [&'static str]),
+    /// Optional. Left empty, the default is used.
+    Path,
+    /// Words separated by spaces.
+    List,
+}
+
+impl FieldKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldKind::Number { .. } => "number",
+            FieldKind::Text => "text",
+            FieldKind::Bool => "bool",
+            FieldKind::Choice(_) => "choice",
+            FieldKind::Path => "path",
+            FieldKind::List => "list",
+        }
+    }
+
+    /// Checks what types alone can't.
+    fn validate(&self, value: &toml::Value) -> anyhow::Result<()> {
+        if let (FieldKind::Number { min }, Some(n)) = (self, value.as_integer()) {
+            anyhow::ensure!(n >= *min as i64, "Must be at least {min}");
+        }
+        Ok(())
+    }
+
+    /// Turns what the user typed into a TOML value. `None` means unset.
+    fn parse(&self, input: &str) -> anyhow::Result<Option<toml::Value>> {
+        let input = input.trim();
+
+        let value = match self {
+            FieldKind::Number { min } => {
+                let n: u64 = input
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("{input:?} isn't a whole number"))?;
+                anyhow::ensure!(n >= *min, "Must be at least {min}");
+                toml::Value::Integer(i64::try_from(n)?)
+            }
+            FieldKind::Text => toml::Value::String(input.to_owned()),
+            FieldKind::Bool => toml::Value::Boolean(
+                input
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Must be true or false"))?,
+            ),
+            FieldKind::Choice(choices) => {
+                anyhow::ensure!(
+                    choices.contains(&input),
+                    "Must be one of: {}",
+                    choices.join(", ")
+                );
+                toml::Value::String(input.to_owned())
+            }
+            FieldKind::Path if input.is_empty() => return Ok(None),
+            FieldKind::Path => toml::Value::String(input.to_owned()),
+            FieldKind::List => toml::Value::Array(
+                input
+                    .split_whitespace()
+                    .map(|w| toml::Value::String(w.to_owned()))
+                    .collect(),
+            ),
+        };
+
+        Ok(Some(value))
+    }
+}
+
+/// A setting, named by its path in config.toml.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Field {
+    pub key: &'static str,
+    pub kind: FieldKind,
+    pub description: &'static str,
+}
+
+/// Every setting, in the order the options page lists them.
+pub const FIELDS: &[Field] = &[
+    Field {
+        key: "chapter_page_size",
+        kind: FieldKind::Number { min: 1 },
+        description: "Chapters shown per page on a comic's page",
+    },
+    Field {
+        key: "anilist_token",
+        kind: FieldKind::Text,
+        description: "AniList access token",
+    },
+    Field {
+        key: "download_dir",
+        kind: FieldKind::Path,
+        description: "Where chapters are saved, empty for the downloads folder in the data dir",
+    },
+    Field {
+        key: "download_concurrency",
+        kind: FieldKind::Number { min: 1 },
+        description: "How many chapters the download queue fetches at once",
+    },
+    Field {
+        key: "export_cbz",
+        kind: FieldKind::Bool,
+        description: "Also pack downloaded chapters into CBZ archives",
+    },
+    Field {
+        key: "reader.builtin",
+        kind: FieldKind::Bool,
+        description: "Read chapters inside the TUI instead of opening the viewer",
+    },
+    Field {
+        key: "reader.graphics",
+        kind: FieldKind::Choice(&["auto", "kitty", "sixel", "halfblock"]),
+        description: "How the built-in reader draws images",
+    },
+    Field {
+        key: "viewer.command",
+        kind: FieldKind::Text,
+        description: "Program that opens chapters when the built-in reader is off",
+    },
+    Field {
+        key: "viewer.args",
+        kind: FieldKind::List,
+        description: "Its arguments. {dir}, {first} and {pages} are replaced",
+    },
+    Field {
+        key: "viewer.wait",
+        kind: FieldKind::Bool,
+        description: "Stay on the loading screen until the viewer exits",
+    },
+    Field {
+        key: "keys.preset",
+        kind: FieldKind::Choice(&["default", "vim"]),
+        description: "Key bindings to start from. Tables like [keys.comic] change single actions",
+    },
+    Field {
+        key: "theme.name",
+        kind: FieldKind::Choice(&["default", "gruvbox", "monochrome"]),
+        description: "Colors to draw with. Setting NO_COLOR picks monochrome",
+    },
+    Field {
+        key: "theme.file",
+        kind: FieldKind::Path,
+        description: "TOML file with colors that replace some of the theme's, e.g. border = \
+                      \"blue\"",
+    },
+    Field {
+        key: "log_level",
+        kind: FieldKind::Choice(&["off", "error", "warn", "info", "debug", "trace"]),
+        description: "How much goes into the log file. NIKA_LOG replaces it",
+    },
+    Field {
+        key: "sources.disabled",
+        kind: FieldKind::List,
+        description: "Ids of the sources to leave out, e.g. mangadex",
+    },
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ReaderConfig {
+    /// Read chapters inside the TUI instead of opening them with the viewer.
+    pub builtin: bool,
+    pub graphics: GraphicsProtocol,
+}
+
+impl Default for ReaderConfig {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            graphics: GraphicsProtocol::Auto,
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             anilist_token: Default::default(),
-            chapter_page_size: 25,
+            chapter_page_size: default_chapter_page_size(),
+            viewer: ViewerConfig::default(),
+            reader: ReaderConfig::default(),
+            download_concurrency: default_download_concurrency(),
+            download_dir: None,
+            export_cbz: false,
+            keys: KeyConfig::default(),
+            theme: ThemeConfig::default(),
+            log_level: LogLevel::default(),
+            sources: SourcesConfig::default(),
         }
     }
 }
 
 impl Config {
-    pub fn get_or_default() -> Self {
-        let home_dir = env::var_os("HOME");
-
-        if let Some(dir) = home_dir {
-            let s = dir.into_string().unwrap();
-            let fpath = format!("{s}/{DEFAULT_CONFIG_DIR}/config.toml");
-
-            Self::ensure_conditions();
-
-            match fs::read_to_string(&fpath) {
-                // Note: there might still be errors in the config, so unwrap_or_default() is
-                // necessary.
-                Ok(data) => toml::from_str(data.as_str()).unwrap_or_default(),
-                Err(_) => {
-                    fs::write(fpath, "").unwrap();
-                    Self::default()
+    /// Reads the config file, see [`Config::load_from`].
+    pub fn load() -> (Self, Vec<String>) {
+        Self::load_from(&paths::get().config_file)
+    }
+
+    /// Reads the config file at `path`, creating it if needed. Also returns what was wrong with
+    /// it, since invalid settings are replaced by their defaults.
+    pub fn load_from(path: &Path) -> (Self, Vec<String>) {
+        match fs::read_to_string(path) {
+            Ok(data) => Self::parse(&data),
+            Err(_) => {
+                // Whether or not this succeeds is irrelevant, saving creates it too.
+                if let Some(dir) = path.parent() {
+                    let _ = fs::create_dir_all(dir);
                 }
+                let _ = fs::write(path, "");
+                (Self::default(), Vec::new())
             }
-        } else {
-            Self::default()
         }
     }
 
+    /// Reads settings one by one, so a mistake only costs that setting.
+    pub fn parse(data: &str) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+
+        let config = match data.parse::<toml::Table>() {
+            Ok(table) => {
+                let mut merged = toml::Table::try_from(Self::default()).unwrap_or_default();
+                merge(&mut merged, table, &mut errors);
+                toml::Value::Table(merged).try_into().unwrap_or_default()
+            }
+            Err(e) => {
+                errors.push(format!("config.toml isn't valid TOML: {}", e.message()));
+                Self::default()
+            }
+        };
+
+        (config, errors)
+    }
+
     pub fn chapter_page_size(&self) -> usize {
         self.chapter_page_size
     }
@@ -51,69 +304,490 @@ impl Config {
         &self.anilist_token
     }
 
-    /// If dir doesn't exist, create it. As for the config file itself, it's handled on
-    /// get_or_default(), so no need to handle the scenario where it doesn't exist here.
-    fn ensure_conditions() {
-        // Whether or not this command succeeds or not is irrelevant.
-        let home_dir = env::var_os("HOME");
+    pub fn viewer(&self) -> &ViewerConfig {
+        &self.viewer
+    }
+
+    pub fn reader(&self) -> &ReaderConfig {
+        &self.reader
+    }
+
+    pub fn download_concurrency(&self) -> usize {
+        self.download_concurrency
+    }
+
+    pub fn download_dir(&self) -> PathBuf {
+        match &self.download_dir {
+            Some(dir) => dir.to_owned(),
+            None => paths::get().data_file(DOWNLOAD_DIR),
+        }
+    }
+
+    pub fn download_options(&self) -> DownloadOptions {
+        DownloadOptions {
+            root: self.download_dir(),
+            cbz: self.export_cbz,
+        }
+    }
+
+    pub fn log_level(&self) -> LogLevel {
+        self.log_level
+    }
+
+    pub fn sources(&self) -> &SourcesConfig {
+        &self.sources
+    }
+
+    /// What each key does, and the bindings that conflict.
+    pub fn keymap(&self) -> (Keymap, Vec<String>) {
+        Keymap::new(&self.keys)
+    }
+
+    /// The colors to draw with, and why the theme file couldn't be read.
+    pub fn theme(&self) -> (Theme, Option<String>) {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Theme::new(&self.theme, no_color)
+    }
+
+    /// The value of a field as it's written in config.toml, or `None` if it's unset.
+    fn value(&self, key: &str) -> Option<toml::Value> {
+        let table = toml::Value::try_from(self).ok()?;
+        key.split('.').try_fold(&table, |v, k| v.get(k)).cloned()
+    }
+
+    /// The value of a field as shown on the options page.
+    pub fn get(&self, key: &str) -> String {
+        match self.value(key) {
+            Some(toml::Value::String(s)) => s.to_owned(),
+            Some(toml::Value::Array(items)) => items
+                .iter()
+                .map(|i| {
+                    i.as_str()
+                        .map(str::to_owned)
+                        .unwrap_or_else(|| i.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            Some(value) => value.to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Sets a field from what the user typed, leaving the config as it was if that's invalid.
+    pub fn set(&mut self, key: &str, input: &str) -> anyhow::Result<()> {
+        let field = FIELDS
+            .iter()
+            .find(|f| f.key == key)
+            .ok_or_else(|| anyhow::anyhow!("Unknown setting {key}"))?;
+        let value = field.kind.parse(input)?;
+
+        let mut table = toml::Table::try_from(&*self)?;
+        insert(&mut table, key, value)?;
+
+        *self = toml::Value::Table(table).try_into()?;
+        Ok(())
+    }
+
+    /// Writes the settings that differ from config.toml into it, see [`Config::save_to`].
+    pub fn save(&self) -> anyhow::Result<bool> {
+        self.save_to(&paths::get().config_file)
+    }
+
+    /// Writes the settings that differ from the file at `path` into it, keeping the user's
+    /// comments and formatting. Returns whether anything changed.
+    ///
+    /// A file that isn't valid TOML is never overwritten. The new one is written next to it and
+    /// renamed over it, so a crash can't leave it half written either.
+    pub fn save_to(&self, path: &Path) -> anyhow::Result<bool> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut document: DocumentMut = text
+            .parse()
+            .map_err(|e| anyhow::anyhow!("config.toml isn't valid TOML, fix it first: {e}"))?;
+        let (on_disk, _) = Self::parse(&text);
+
+        let mut changed = false;
+        for field in FIELDS {
+            let value = self.value(field.key);
+            if value != on_disk.value(field.key) {
+                set_item(&mut document, field.key, value)?;
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(false);
+        }
+
+        helpers::write_atomically(path, &document.to_string())?;
+
+        Ok(true)
+    }
+}
+
+/// Adds the user's settings in `table` to `merged`, which holds the defaults. Settings that are
+/// unknown or invalid are left out and reported instead.
+fn merge(merged: &mut toml::Table, table: toml::Table, errors: &mut Vec<String>) {
+    for (key, value) in flatten(table, "") {
+        let kind = match FIELDS.iter().find(|f| f.key == key) {
+            Some(field) => Some(field.kind),
+            // Checked when deserializing, like whether the context and action exist.
+            None if is_binding(&key) => None,
+            None => {
+                errors.push(format!("{key}: unknown setting"));
+                continue;
+            }
+        };
 
-        if let Some(dir) = home_dir {
-            let s = dir.into_string().unwrap();
-            let path = format!("{s}/{DEFAULT_CONFIG_DIR}");
-            let _ = fs::create_dir(path);
+        let valid = kind.map_or(Ok(()), |kind| kind.validate(&value));
+        let result = valid.and_then(|_| {
+            let mut candidate = merged.clone();
+            insert(&mut candidate, &key, Some(value))?;
+            toml::Value::Table(candidate.clone()).try_into::<Config>()?;
+            Ok(candidate)
+        });
+        match result {
+            Ok(candidate) => *merged = candidate,
+            Err(e) => errors.push(format!("{key}: {}", first_line(&e.to_string()))),
         }
     }
 }
 
-impl Drop for Config {
-    fn drop(&mut self) {
-        let home_dir = env::var_os("HOME").unwrap().into_string().unwrap();
-        let fpath = format!("{home_dir}/{DEFAULT_CONFIG_DIR}/config.toml");
+/// The settings in a table with their dotted keys, e.g. `reader.graphics`.
+fn flatten(table: toml::Table, prefix: &str) -> Vec<(String, toml::Value)> {
+    let mut settings = Vec::new();
 
-        let s = toml::to_string(&self).unwrap();
-        std::fs::write(fpath, s).unwrap();
+    for (name, value) in table {
+        let key = format!("{prefix}{name}");
+        let nested = format!("{key}.");
+
+        match value {
+            toml::Value::Table(inner)
+                if FIELDS.iter().any(|f| f.key.starts_with(&nested)) || is_binding(&key) =>
+            {
+                settings.extend(flatten(inner, &nested))
+            }
+            value => settings.push((key, value)),
+        }
     }
+
+    settings
+}
+
+/// Whether a key is inside one of the `[keys.<context>]` tables.
+fn is_binding(key: &str) -> bool {
+    key.starts_with("keys.")
+}
+
+/// Sets or, with `None`, removes the setting at a dotted key.
+fn insert(table: &mut toml::Table, key: &str, value: Option<toml::Value>) -> anyhow::Result<()> {
+    let (parents, name) = match key.rsplit_once('.') {
+        Some((parents, name)) => (Some(parents), name),
+        None => (None, key),
+    };
+
+    let mut parent = table;
+    for part in parents.into_iter().flat_map(|p| p.split('.')) {
+        parent = parent
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("{part} isn't a table"))?;
+    }
+
+    match value {
+        Some(value) => parent.insert(name.to_owned(), value),
+        None => parent.remove(name),
+    };
+    Ok(())
+}
+
+/// toml's messages quote the whole document after the first line.
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+/// Sets or, with `None`, removes a setting in a document, keeping the comment after it.
+fn set_item(
+    document: &mut DocumentMut,
+    key: &str,
+    value: Option<toml::Value>,
+) -> anyhow::Result<()> {
+    let (parents, name) = match key.rsplit_once('.') {
+        Some((parents, name)) => (Some(parents), name),
+        None => (None, key),
+    };
+
+    let mut table = document.as_table_mut();
+    for part in parents.into_iter().flat_map(|p| p.split('.')) {
+        table = table
+            .entry(part)
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("{part} isn't a table"))?;
+    }
+
+    let Some(value) = value else {
+        table.remove(name);
+        return Ok(());
+    };
+
+    let mut value: toml_edit::Value = value.to_string().parse()?;
+    match table.get_mut(name).and_then(|i| i.as_value_mut()) {
+        // Replaced in place, since inserting would also reset the key's formatting.
+        Some(old) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        None => {
+            table.insert(name, toml_edit::Item::Value(value));
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use std::{env, fs};
 
-    use super::Config;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{Config, FIELDS, ReaderConfig};
+    use crate::graphics::GraphicsProtocol;
+    use crate::keymap::{Action, Context, KeyConfig};
+    use crate::logging::LogLevel;
+    use crate::models::sources::registry::SourcesConfig;
+    use crate::test_utils;
+    use crate::theme::ThemeConfig;
+    use crate::viewer::ViewerConfig;
 
     #[test]
     fn load_existing_or_new() {
-        let fname = "config.toml";
+        let dir = env::temp_dir().join("nika-config-test").join("load");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("config.toml");
+
+        // Missing files are created empty.
+        let (config, errors) = Config::load_from(&path);
+        assert!(errors.is_empty());
+        assert_eq!(config.chapter_page_size(), 25);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
 
         let config = Config {
             anilist_token: "lkjasdjklasjlkdasjlk".into(),
-            chapter_page_size: 25,
+            chapter_page_size: 30,
+            viewer: ViewerConfig::default(),
+            reader: ReaderConfig::default(),
+            download_concurrency: 2,
+            download_dir: None,
+            export_cbz: false,
+            keys: KeyConfig::default(),
+            theme: ThemeConfig::default(),
+            log_level: LogLevel::default(),
+            sources: SourcesConfig::default(),
         };
+        assert!(config.save_to(&path).unwrap());
+
+        let (loaded, errors) = Config::load_from(&path);
+        assert!(errors.is_empty());
+        assert_eq!(loaded.anilist_token(), "lkjasdjklasjlkdasjlk");
+        assert_eq!(loaded.chapter_page_size(), 30);
+    }
 
-        let home_dir = env::var_os("HOME");
+    #[test]
+    fn edits_fields_and_rejects_invalid_values() {
+        let mut config = Config::default();
 
-        if let Some(dir) = home_dir {
-            let path = format!("{}/.config/nika-tui/", dir.into_string().unwrap());
-            let stuff = toml::to_string(&config).unwrap();
+        config.set("chapter_page_size", " 40 ").unwrap();
+        config.set("reader.graphics", "sixel").unwrap();
+        config.set("viewer.args", "--fullscreen {first}").unwrap();
+        config.set("download_dir", "/tmp/comics").unwrap();
+        config.set("export_cbz", "true").unwrap();
 
-            // Whether or not this command succeeds or not is irrelevant.
-            let _ = fs::create_dir(&path);
+        assert_eq!(config.chapter_page_size(), 40);
+        assert_eq!(config.reader().graphics, GraphicsProtocol::Sixel);
+        assert_eq!(config.viewer().args, ["--fullscreen", "{first}"]);
+        assert_eq!(config.get("viewer.args"), "--fullscreen {first}");
+        assert_eq!(config.get("download_dir"), "/tmp/comics");
+        assert_eq!(config.get("export_cbz"), "true");
 
-            let full_path = format!("{path}{fname}");
+        // Empty paths go back to the default.
+        config.set("download_dir", "").unwrap();
+        assert_eq!(config.get("download_dir"), "");
 
-            match fs::write(full_path, &stuff) {
-                Ok(_) => println!("Done!"),
-                Err(_) => panic!("Couldn't write to file."),
-            };
-        } else {
-            panic!("What? Couldn't find home dir.")
+        assert!(config.set("chapter_page_size", "0").is_err());
+        assert!(config.set("chapter_page_size", "many").is_err());
+        assert!(config.set("reader.graphics", "ascii").is_err());
+        assert!(config.set("export_cbz", "yes").is_err());
+        assert!(config.set("colour", "red").is_err());
+        assert_eq!(config.chapter_page_size(), 40);
+
+        // Every listed field can be read back.
+        for field in FIELDS {
+            let value = config.get(field.key);
+            config.set(field.key, &value).unwrap();
         }
     }
 
     #[test]
-    fn test_get_or_default() {
-        let s = Config::get_or_default();
+    fn keeps_valid_settings_and_reports_the_rest() {
+        let data = r#"
+            chapter_page_size = 0
+            anilist_token = "token"
+            download_concurency = 4
+            export_cbz = "yes"
+
+            [reader]
+            builtin = false
+            graphics = "ascii"
+        "#;
+
+        let (config, errors) = Config::parse(data);
+
+        assert_eq!(config.anilist_token(), "token");
+        assert_eq!(config.chapter_page_size(), 25);
+        assert!(!config.reader().builtin);
+        assert_eq!(config.reader().graphics, GraphicsProtocol::Auto);
+        assert!(!config.download_options().cbz);
+
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].starts_with("chapter_page_size: Must be at least 1"));
+        assert_eq!(errors[1], "download_concurency: unknown setting");
+        assert!(errors[2].starts_with("export_cbz: "));
+        assert!(errors[3].starts_with("reader.graphics: "));
+    }
+
+    #[test]
+    fn reads_key_bindings_one_by_one() {
+        let data = r#"
+            [keys]
+            preset = "vim"
+
+            [keys.comic]
+            toggle_library = "L"
+            download_marked = ["d", "ctrl-s"]
+            fly = "f"
+            mark = "hyper-m"
+
+            [keys.nowhere]
+            quit = "q"
+        "#;
+
+        let (config, errors) = Config::parse(data);
+        let (keymap, conflicts) = config.keymap();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+        assert_eq!(config.get("keys.preset"), "vim");
+        assert_eq!(
+            keymap.action(Context::Comic, key('L')),
+            Some(Action::ToggleLibrary)
+        );
+        assert_eq!(keymap.action(Context::Comic, key('j')), Some(Action::Down));
+        assert_eq!(
+            keymap.action(
+                Context::Comic,
+                KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL)
+            ),
+            Some(Action::DownloadMarked)
+        );
+        // The preset's key is kept, since the binding was invalid.
+        assert_eq!(keymap.action(Context::Comic, key(' ')), Some(Action::Mark));
+        assert!(conflicts.is_empty());
+
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("keys.comic.fly: "));
+        assert!(errors[1].starts_with("keys.comic.mark: Unknown modifier"));
+        assert!(errors[2].starts_with("keys.nowhere.quit: "));
+
+        // Saving doesn't touch the bindings.
+        let path = test_utils::temp_file("nika-config-test", "keys", data);
+        let mut config = config;
+        config.set("keys.preset", "default").unwrap();
+        assert!(config.save_to(&path).unwrap());
+        let (saved, _) = Config::load_from(&path);
+        assert_eq!(saved.get("keys.preset"), "default");
+        assert_eq!(saved.keys.bindings, config.keys.bindings);
+    }
+
+    #[test]
+    fn missing_settings_use_defaults() {
+        let (config, errors) = Config::parse("chapter_page_size = 10\n[viewer]\nwait = true");
+
+        assert!(errors.is_empty());
+        assert_eq!(config.chapter_page_size(), 10);
+        assert!(config.viewer().wait);
+        assert_eq!(config.viewer().command, ViewerConfig::default().command);
+        assert_eq!(config.download_concurrency(), 2);
+    }
+
+    #[test]
+    fn broken_files_are_reported_and_left_alone() {
+        let path = test_utils::temp_file("nika-config-test", "broken", "chapter_page_size = [");
+        let (mut config, errors) = Config::parse(&fs::read_to_string(&path).unwrap());
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("config.toml isn't valid TOML"));
+        assert_eq!(config.chapter_page_size(), 25);
+
+        config.set("chapter_page_size", "30").unwrap();
+        assert!(config.save_to(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "chapter_page_size = [");
+    }
+
+    #[test]
+    fn saves_only_changes_and_keeps_formatting() {
+        let original = "\
+# Written by hand.
+chapter_page_size   = 10 # fits my screen
+graphics = \"ascii\"
+
+[reader]
+builtin = false
+";
+        let path = test_utils::temp_file("nika-config-test", "save", original);
+        let (mut config, _) = Config::parse(original);
+
+        // Nothing differs from the file, so it's left alone.
+        assert!(!config.save_to(&path).unwrap());
+
+        config.set("chapter_page_size", "40").unwrap();
+        config.set("reader.graphics", "kitty").unwrap();
+        assert!(config.save_to(&path).unwrap());
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            saved,
+            "\
+# Written by hand.
+chapter_page_size   = 40 # fits my screen
+graphics = \"ascii\"
+
+[reader]
+builtin = false
+graphics = \"kitty\"
+"
+        );
+        assert!(!path.with_extension("toml.tmp").exists());
+
+        let (reloaded, _) = Config::parse(&saved);
+        assert_eq!(reloaded.chapter_page_size(), 40);
+        assert!(!config.save_to(&path).unwrap());
+    }
+
+    #[test]
+    fn copies_left_behind_dont_undo_a_saved_change() {
+        let path = test_utils::temp_file("nika-config-test", "stale", "chapter_page_size = 10\n");
+        let (stale, _) = Config::load_from(&path);
+
+        // What the options page does, while the app still holds the old config.
+        let mut edited = stale.clone();
+        edited.set("chapter_page_size", "40").unwrap();
+        assert!(edited.save_to(&path).unwrap());
+        drop(stale);
 
-        println!("{:?}", s);
+        let (reloaded, _) = Config::load_from(&path);
+        assert_eq!(reloaded.chapter_page_size(), 40);
     }
 }
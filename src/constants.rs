@@ -1 +1,8 @@
-pub const DEFAULT_CONFIG_DIR: &str = ".config/nika-tui";
+pub const APP_DIR: &str = "nika-tui";
+pub const CONFIG_FILE: &str = "config.toml";
+/// Inside the data dir.
+pub const DOWNLOAD_DIR: &str = "downloads";
+pub const LIBRARY_FILE: &str = "library.toml";
+pub const HISTORY_FILE: &str = "history.toml";
+/// Inside the config dir, one TOML file per source.
+pub const SOURCES_DIR: &str = "sources";
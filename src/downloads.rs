@@ -0,0 +1,771 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::fs;
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+use crate::app::NikaAction;
+use crate::cbz;
+use crate::helpers::MissingPages;
+use crate::models::comic::{Chapter, Comic, ComicInfo};
+use crate::progress::Progress;
+use crate::traits::Source;
+
+/// Characters that can't be part of a file name on at least one common platform.
+const RESERVED: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Longer names get cut, since most file systems stop at 255 bytes.
+const MAX_NAME_LEN: usize = 100;
+
+/// Turns a comic or chapter name into something usable as a directory name.
+pub fn sanitize(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match RESERVED.contains(&c) || c.is_control() {
+            true => '_',
+            false => c,
+        })
+        .collect();
+
+    // Leading dots would hide the directory, trailing ones are dropped by Windows.
+    let trimmed: String = replaced
+        .trim_matches(|c: char| c == '.' || c.is_whitespace())
+        .chars()
+        .take(MAX_NAME_LEN)
+        .collect();
+
+    match trimmed.trim_end() {
+        "" => String::from("_"),
+        name => name.to_owned(),
+    }
+}
+
+/// Where a chapter is stored: `<root>/<source>/<comic>/<chapter> (<id>)`. Names aren't enough to
+/// tell chapters apart, e.g. one chapter in two languages, so the id comes from its url.
+pub fn chapter_dir(root: &Path, source: &str, comic: &Comic, chapter: &Chapter) -> PathBuf {
+    let name = format!(
+        "{} ({})",
+        sanitize(&chapter.name),
+        short_hash(&chapter.source)
+    );
+    root.join(sanitize(source))
+        .join(sanitize(&comic.name))
+        .join(name)
+}
+
+/// 8 hex digits of the FNV-1a hash of `text`, which stays the same across runs and versions.
+fn short_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:08x}", hash as u32)
+}
+
+/// A chapter being downloaded, deleted when dropped unless kept. Since aborting a task drops its
+/// future, cancelled downloads don't leave anything behind either.
+struct PartialDir {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl Drop for PartialDir {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// Where and how downloaded chapters are saved.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    pub root: PathBuf,
+    /// Also pack chapters into CBZ archives, see [`cbz::export`].
+    pub cbz: bool,
+}
+
+/// Downloads a chapter into its directory under the download root, unless it's already there.
+/// Returns the chapter's directory.
+///
+/// When only some pages fail, the others are kept aside and the error is a [`MissingPages`]
+/// pointing at them. The chapter is downloaded again the next time.
+pub async fn fetch_chapter(
+    source: &dyn Source,
+    comic: &Comic,
+    info: &ComicInfo,
+    chapter: &Chapter,
+    options: &DownloadOptions,
+    sender: Option<UnboundedSender<NikaAction>>,
+) -> anyhow::Result<String> {
+    let dir = chapter_dir(&options.root, source.name(), comic, chapter);
+
+    if fs::try_exists(&dir).await? {
+        tracing::debug!(dir = %dir.display(), "already downloaded");
+    } else {
+        // Pages go somewhere else first, so an interrupted download never looks complete.
+        let mut partial = dir.clone().into_os_string();
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+
+        let _ = fs::remove_dir_all(&partial).await;
+        fs::create_dir_all(&partial).await?;
+        let mut partial = PartialDir {
+            path: partial,
+            keep: false,
+        };
+
+        if let Err(e) = source
+            .download_chapter(chapter, &partial.path, sender)
+            .await
+        {
+            partial.keep = e
+                .downcast_ref::<MissingPages>()
+                .is_some_and(|m| m.is_partial());
+            return Err(e);
+        }
+        fs::rename(&partial.path, &dir).await?;
+        partial.keep = true;
+    }
+
+    if options.cbz {
+        let (dir, comic, info, chapter) =
+            (dir.clone(), comic.clone(), info.clone(), chapter.clone());
+        tokio::task::spawn_blocking(move || cbz::export(&dir, &comic, &info, &chapter)).await??;
+    }
+
+    Ok(dir.to_string_lossy().into_owned())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadState {
+    Queued,
+    Downloading,
+    Paused,
+    /// Holds the directory the chapter was saved to.
+    Done(String),
+    /// Saved, except for the listed pages.
+    Partial(String, Vec<usize>),
+    Failed(String),
+    Cancelled,
+}
+
+pub struct DownloadItem {
+    pub id: usize,
+    pub comic: Comic,
+    pub info: ComicInfo,
+    pub chapter: Chapter,
+    pub source: Arc<dyn Source>,
+    pub state: DownloadState,
+    /// Between 0 and 1.
+    pub progress: f64,
+    task: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct Queue {
+    items: Vec<DownloadItem>,
+    next_id: usize,
+    concurrency: usize,
+    options: DownloadOptions,
+}
+
+/// Downloads chapters in the background, at most `concurrency` at a time. Cloning gives another
+/// handle to the same queue.
+#[derive(Clone)]
+pub struct DownloadManager {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl DownloadManager {
+    pub fn new(concurrency: usize, options: DownloadOptions) -> Self {
+        let queue = Queue {
+            concurrency: concurrency.max(1),
+            options,
+            ..Default::default()
+        };
+
+        Self {
+            queue: Arc::new(Mutex::new(queue)),
+        }
+    }
+
+    /// Applies new settings. Running downloads keep going, even above a lower limit.
+    pub fn reconfigure(&self, concurrency: usize, options: DownloadOptions) {
+        let mut queue = self.lock();
+        queue.concurrency = concurrency.max(1);
+        queue.options = options;
+        self.schedule(&mut queue);
+    }
+
+    /// Queues chapters of a comic and starts as many as the limit allows.
+    pub fn enqueue(
+        &self,
+        comic: &Comic,
+        info: &ComicInfo,
+        source: Arc<dyn Source>,
+        chapters: Vec<Chapter>,
+    ) {
+        let mut queue = self.lock();
+        tracing::info!(comic = comic.name, chapters = chapters.len(), "queued");
+
+        for chapter in chapters {
+            let id = queue.next_id;
+            queue.next_id += 1;
+
+            queue.items.push(DownloadItem {
+                id,
+                comic: comic.clone(),
+                info: info.clone(),
+                chapter,
+                source: source.clone(),
+                state: DownloadState::Queued,
+                progress: 0.0,
+                task: None,
+            });
+        }
+
+        self.schedule(&mut queue);
+    }
+
+    /// Gives access to the items, e.g. to draw them.
+    pub fn with_items<R>(&self, f: impl FnOnce(&[DownloadItem]) -> R) -> R {
+        f(&self.lock().items)
+    }
+
+    /// Pauses a queued or running download, or resumes a paused one. A running download starts
+    /// over when resumed.
+    pub fn toggle_pause(&self, id: usize) {
+        self.transition(id, |state| match state {
+            DownloadState::Queued | DownloadState::Downloading => Some(DownloadState::Paused),
+            DownloadState::Paused => Some(DownloadState::Queued),
+            _ => None,
+        });
+    }
+
+    pub fn cancel(&self, id: usize) {
+        self.transition(id, |state| match state {
+            DownloadState::Queued | DownloadState::Downloading | DownloadState::Paused => {
+                Some(DownloadState::Cancelled)
+            }
+            _ => None,
+        });
+    }
+
+    pub fn retry(&self, id: usize) {
+        self.transition(id, |state| match state {
+            DownloadState::Partial(..) | DownloadState::Failed(_) | DownloadState::Cancelled => {
+                Some(DownloadState::Queued)
+            }
+            _ => None,
+        });
+    }
+
+    /// Removes finished, failed and cancelled downloads from the list.
+    pub fn clear_finished(&self) {
+        self.lock().items.retain(|i| {
+            matches!(
+                i.state,
+                DownloadState::Queued | DownloadState::Downloading | DownloadState::Paused
+            )
+        });
+    }
+
+    fn transition(&self, id: usize, f: impl FnOnce(&DownloadState) -> Option<DownloadState>) {
+        let mut queue = self.lock();
+
+        if let Some(item) = queue.items.iter_mut().find(|i| i.id == id) {
+            if let Some(state) = f(&item.state) {
+                tracing::debug!(id, from = ?item.state, to = ?state, "download state changed");
+                if let Some(task) = item.task.take() {
+                    task.abort();
+                }
+                if state == DownloadState::Queued {
+                    item.progress = 0.0;
+                }
+                item.state = state;
+            }
+        }
+
+        self.schedule(&mut queue);
+    }
+
+    /// Starts queued downloads until the concurrency limit is reached.
+    fn schedule(&self, queue: &mut MutexGuard<'_, Queue>) {
+        loop {
+            let running = queue
+                .items
+                .iter()
+                .filter(|i| i.state == DownloadState::Downloading)
+                .count();
+
+            if running >= queue.concurrency {
+                break;
+            }
+
+            let options = queue.options.clone();
+
+            let Some(item) = queue
+                .items
+                .iter_mut()
+                .find(|i| i.state == DownloadState::Queued)
+            else {
+                break;
+            };
+
+            item.state = DownloadState::Downloading;
+            item.task = Some(self.spawn(item, options));
+        }
+    }
+
+    fn spawn(&self, item: &DownloadItem, options: DownloadOptions) -> JoinHandle<()> {
+        let manager = self.clone();
+        let id = item.id;
+        let source = item.source.clone();
+        let (comic, info, chapter) = (item.comic.clone(), item.info.clone(), item.chapter.clone());
+
+        let span = tracing::info_span!(
+            "download",
+            id,
+            source = source.name(),
+            comic = comic.name,
+            chapter = chapter.name
+        );
+
+        let task = async move {
+            tracing::info!("started");
+            // Sources report progress the same way they do for the loading screen.
+            let (tx, mut rx) = unbounded_channel();
+            let progress = manager.clone();
+            let forward = tokio::spawn(async move {
+                let mut state = Progress::default();
+                while let Some(action) = rx.recv().await {
+                    if let NikaAction::Progress(update) = action {
+                        state.apply(update);
+                        progress.update(id, |item| item.progress = state.fraction());
+                    }
+                }
+            });
+
+            let result = fetch_chapter(&*source, &comic, &info, &chapter, &options, Some(tx)).await;
+            forward.abort();
+
+            match &result {
+                Ok(path) => tracing::info!(path, "done"),
+                Err(e) => tracing::warn!("failed: {e:#}"),
+            }
+
+            let mut queue = manager.lock();
+            // Paused or cancelled while finishing up, so the result no longer matters.
+            let item = queue
+                .items
+                .iter_mut()
+                .find(|i| i.id == id && i.state == DownloadState::Downloading);
+            if let Some(item) = item {
+                item.task = None;
+                item.state = match result {
+                    Ok(path) => {
+                        item.progress = 1.0;
+                        DownloadState::Done(path)
+                    }
+                    Err(e) => match e.downcast_ref::<MissingPages>() {
+                        Some(m) if m.is_partial() => {
+                            item.progress = 1.0;
+                            DownloadState::Partial(
+                                m.dir.to_string_lossy().into_owned(),
+                                m.pages.clone(),
+                            )
+                        }
+                        _ => DownloadState::Failed(format!("{e:#}")),
+                    },
+                };
+            }
+            manager.schedule(&mut queue);
+        };
+
+        tokio::spawn(task.instrument(span))
+    }
+
+    fn update(&self, id: usize, f: impl FnOnce(&mut DownloadItem)) {
+        if let Some(item) = self.lock().items.iter_mut().find(|i| i.id == id) {
+            f(item);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        // A panic while holding the lock can't leave the queue in a broken state.
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+    use std::{env, fs};
+
+    use async_trait::async_trait;
+    use tokio::sync::Notify;
+    use tokio::sync::mpsc::UnboundedSender;
+
+    use super::{
+        DownloadManager, DownloadOptions, DownloadState, chapter_dir, fetch_chapter, sanitize,
+    };
+    use crate::app::NikaAction;
+    use crate::cbz;
+    use crate::helpers::MissingPages;
+    use crate::models::comic::{Chapter, Comic, ComicInfo};
+    use crate::progress::{self, ProgressUpdate};
+    use crate::traits::Source;
+
+    /// Pretends to download, keeping track of how many downloads run at once.
+    #[derive(Default)]
+    struct FakeSource {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+        calls: AtomicUsize,
+        fail: AtomicBool,
+        /// Loses the second of two pages.
+        lose_page: AtomicBool,
+        /// Holds downloads once they've started, until `release` is notified.
+        hold: AtomicBool,
+        started: Notify,
+        release: Notify,
+    }
+
+    #[async_trait]
+    impl Source for FakeSource {
+        async fn search(&self, _query: &str) -> reqwest::Result<Vec<Comic>> {
+            Ok(Vec::new())
+        }
+
+        fn base_url(&self) -> &str {
+            "http://localhost"
+        }
+
+        async fn get_chapters(&self, _comic: &Comic) -> reqwest::Result<Vec<Chapter>> {
+            Ok(Vec::new())
+        }
+
+        async fn get_info(&self, _comic: &Comic) -> reqwest::Result<Option<ComicInfo>> {
+            Ok(None)
+        }
+
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        async fn download_chapter(
+            &self,
+            chapter: &Chapter,
+            dir: &Path,
+            sender: Option<UnboundedSender<NikaAction>>,
+        ) -> anyhow::Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+
+            if self.hold.load(Ordering::SeqCst) {
+                self.started.notify_one();
+                self.release.notified().await;
+            }
+
+            progress::report(&sender, ProgressUpdate::PageCount(4));
+            for _ in 0..4 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                progress::report(&sender, ProgressUpdate::PageDone);
+            }
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            if self.fail.load(Ordering::SeqCst) {
+                let error = anyhow::anyhow!("404");
+                return Err(error.context(format!("Fetching {}", chapter.name)));
+            }
+            fs::write(dir.join("001.jpeg"), [0xFF, 0xD8, 0xFF])?;
+
+            if self.lose_page.load(Ordering::SeqCst) {
+                return Err(MissingPages {
+                    dir: dir.to_owned(),
+                    pages: vec![2],
+                    total: 2,
+                    reason: String::from("500 Internal Server Error"),
+                }
+                .into());
+            }
+            Ok(())
+        }
+    }
+
+    fn comic() -> Comic {
+        Comic {
+            name: String::from("One Piece"),
+            ..Default::default()
+        }
+    }
+
+    fn chapters(n: usize) -> Vec<Chapter> {
+        (0..n)
+            .map(|i| Chapter::new(&format!("{i}"), &format!("http://localhost/{i}")))
+            .collect()
+    }
+
+    /// An empty download root, only used by one test.
+    fn options(name: &str) -> DownloadOptions {
+        let root = env::temp_dir().join(format!("nika-downloads-test-{name}"));
+        let _ = fs::remove_dir_all(&root);
+
+        DownloadOptions { root, cbz: false }
+    }
+
+    /// The state of `chapters(n)[i]` once downloaded.
+    fn done(root: &Path, i: usize) -> DownloadState {
+        let chapter = chapters(i + 1).pop().unwrap();
+        let dir = chapter_dir(root, "fake", &comic(), &chapter);
+        DownloadState::Done(dir.to_string_lossy().into_owned())
+    }
+
+    fn states(manager: &DownloadManager) -> Vec<DownloadState> {
+        manager.with_items(|items| items.iter().map(|i| i.state.clone()).collect())
+    }
+
+    async fn wait_until_idle(manager: &DownloadManager) {
+        for _ in 0..200 {
+            let busy = states(manager)
+                .iter()
+                .any(|s| matches!(s, DownloadState::Queued | DownloadState::Downloading));
+            if !busy {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("Downloads didn't finish");
+    }
+
+    #[test]
+    fn sanitizes_names() {
+        assert_eq!(sanitize("Chapter 1: A/B?"), "Chapter 1_ A_B_");
+        assert_eq!(sanitize("..secret. "), "secret");
+        assert_eq!(sanitize(".."), "_");
+        assert_eq!(sanitize(&"a".repeat(300)).len(), 100);
+    }
+
+    #[test]
+    fn chapters_with_the_same_name_get_their_own_dirs() {
+        let root = Path::new("/downloads");
+        let dir =
+            |name: &str, url: &str| chapter_dir(root, "fake", &comic(), &Chapter::new(name, url));
+
+        // The same chapter in two languages, and two names that sanitize the same.
+        let english = dir("Chapter 1", "https://mangadex.test/chapter/en-1");
+        let portuguese = dir("Chapter 1", "https://mangadex.test/chapter/pt-br-1");
+        assert_ne!(english, portuguese);
+        assert_ne!(
+            dir("A/B", "https://a.test/1"),
+            dir("A?B", "https://a.test/2")
+        );
+
+        assert_eq!(
+            english,
+            dir("Chapter 1", "https://mangadex.test/chapter/en-1")
+        );
+        let name = english.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("Chapter 1 ("), "{name}");
+    }
+
+    #[tokio::test]
+    async fn reuses_chapters_already_on_disk() {
+        let options = DownloadOptions {
+            cbz: true,
+            ..options("reuse")
+        };
+        let source = FakeSource::default();
+        let chapter = Chapter::new("Chapter 1", "http://localhost/1");
+
+        let first = fetch_chapter(
+            &source,
+            &comic(),
+            &ComicInfo::default(),
+            &chapter,
+            &options,
+            None,
+        )
+        .await
+        .unwrap();
+        let second = fetch_chapter(
+            &source,
+            &comic(),
+            &ComicInfo::default(),
+            &chapter,
+            &options,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(
+            Path::new(&first),
+            chapter_dir(&options.root, "fake", &comic(), &chapter)
+        );
+        assert_eq!(source.calls.load(Ordering::SeqCst), 1);
+        assert!(Path::new(&first).join("001.jpeg").exists());
+        assert!(cbz::cbz_path(Path::new(&first)).exists());
+    }
+
+    #[tokio::test]
+    async fn failed_downloads_leave_nothing_behind() {
+        let options = options("failure");
+        let source = FakeSource::default();
+        source.fail.store(true, Ordering::SeqCst);
+        let chapter = Chapter::new("Chapter 1", "http://localhost/1");
+
+        assert!(
+            fetch_chapter(
+                &source,
+                &comic(),
+                &ComicInfo::default(),
+                &chapter,
+                &options,
+                None
+            )
+            .await
+            .is_err()
+        );
+        assert_eq!(
+            fs::read_dir(options.root.join("fake/One Piece"))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn partial_downloads_are_kept_aside() {
+        let options = options("partial");
+        let source = FakeSource::default();
+        source.lose_page.store(true, Ordering::SeqCst);
+        let chapter = Chapter::new("Chapter 1", "http://localhost/1");
+        let (comic, info) = (comic(), ComicInfo::default());
+        let fetch = || fetch_chapter(&source, &comic, &info, &chapter, &options, None);
+
+        let error = fetch().await.unwrap_err();
+        let missing = error.downcast_ref::<MissingPages>().unwrap();
+        assert_eq!(missing.pages, [2]);
+        assert!(missing.dir.join("001.jpeg").exists());
+        assert!(!chapter_dir(&options.root, "fake", &comic, &chapter).exists());
+
+        // Not mistaken for a complete chapter, so it's downloaded again.
+        source.lose_page.store(false, Ordering::SeqCst);
+        let dir = fetch().await.unwrap();
+        assert!(Path::new(&dir).join("001.jpeg").exists());
+        assert_eq!(source.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn respects_the_concurrency_limit() {
+        let options = options("concurrency");
+        let source = Arc::new(FakeSource::default());
+        let manager = DownloadManager::new(2, options.clone());
+
+        manager.enqueue(&comic(), &ComicInfo::default(), source.clone(), chapters(5));
+        wait_until_idle(&manager).await;
+
+        assert_eq!(source.max_running.load(Ordering::SeqCst), 2);
+        assert!(
+            states(&manager)
+                .iter()
+                .all(|s| matches!(s, DownloadState::Done(_)))
+        );
+        assert!(manager.with_items(|items| items.iter().all(|i| i.progress == 1.0)));
+    }
+
+    #[tokio::test]
+    async fn failed_downloads_can_be_retried() {
+        let options = options("retry");
+        let source = Arc::new(FakeSource::default());
+        source.fail.store(true, Ordering::SeqCst);
+        let manager = DownloadManager::new(1, options.clone());
+
+        manager.enqueue(&comic(), &ComicInfo::default(), source.clone(), chapters(1));
+        wait_until_idle(&manager).await;
+        assert_eq!(
+            states(&manager),
+            // With the cause, not only what was being done.
+            [DownloadState::Failed("Fetching 0: 404".into())]
+        );
+
+        source.fail.store(false, Ordering::SeqCst);
+        manager.retry(0);
+        wait_until_idle(&manager).await;
+        assert_eq!(states(&manager), [done(&options.root, 0)]);
+
+        manager.clear_finished();
+        assert!(states(&manager).is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_downloads_leave_nothing_behind() {
+        let options = options("cancel");
+        let source = Arc::new(FakeSource::default());
+        source.hold.store(true, Ordering::SeqCst);
+        let manager = DownloadManager::new(1, options.clone());
+
+        manager.enqueue(&comic(), &ComicInfo::default(), source.clone(), chapters(1));
+        source.started.notified().await;
+        let dir = chapter_dir(&options.root, "fake", &comic(), &chapters(1)[0]);
+        assert!(dir.with_extension("part").exists());
+
+        manager.cancel(0);
+        assert_eq!(states(&manager), [DownloadState::Cancelled]);
+        // Releasing it now would finish the download, if cancelling hadn't stopped it.
+        source.release.notify_one();
+
+        // The aborted task is dropped by the runtime, whenever it gets to it.
+        let comic_dir = options.root.join("fake/One Piece");
+        for _ in 0..200 {
+            if fs::read_dir(&comic_dir).unwrap().count() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(fs::read_dir(&comic_dir).unwrap().count(), 0);
+        assert_eq!(states(&manager), [DownloadState::Cancelled]);
+        assert_eq!(source.running.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn paused_and_cancelled_downloads_make_room_for_others() {
+        let options = options("pause");
+        let source = Arc::new(FakeSource::default());
+        let manager = DownloadManager::new(1, options.clone());
+
+        manager.enqueue(&comic(), &ComicInfo::default(), source.clone(), chapters(3));
+        manager.toggle_pause(0);
+        manager.cancel(1);
+
+        assert_eq!(
+            states(&manager),
+            [
+                DownloadState::Paused,
+                DownloadState::Cancelled,
+                DownloadState::Downloading
+            ]
+        );
+
+        wait_until_idle(&manager).await;
+        manager.toggle_pause(0);
+        wait_until_idle(&manager).await;
+
+        assert_eq!(
+            states(&manager),
+            [
+                done(&options.root, 0),
+                DownloadState::Cancelled,
+                done(&options.root, 2)
+            ]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt::Write;
+
+use image::{Rgb, RgbImage};
+
+/// Drawn with the top pixel as foreground and the bottom one as background.
+pub const UPPER_HALF: &str = "▀";
+
+/// Top and bottom pixel of every cell, row by row.
+pub type Cells = Vec<Vec<(Rgb<u8>, Option<Rgb<u8>>)>>;
+
+/// Packs two rows of pixels into each cell. The last row of an image with an odd height has no
+/// bottom pixel.
+pub fn encode(image: &RgbImage) -> Cells {
+    let (width, height) = image.dimensions();
+
+    (0..height)
+        .step_by(2)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let bottom = (y + 1 < height).then(|| *image.get_pixel(x, y + 1));
+                    (*image.get_pixel(x, y), bottom)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The same cells as truecolor escape sequences, one line per row.
+pub fn to_ansi(cells: &Cells) -> String {
+    let mut out = String::new();
+
+    for row in cells {
+        for (top, bottom) in row {
+            let _ = write!(out, "\x1b[38;2;{};{};{}", top[0], top[1], top[2]);
+            match bottom {
+                Some(b) => {
+                    let _ = write!(out, ";48;2;{};{};{}m", b[0], b[1], b[2]);
+                }
+                None => out.push_str(";49m"),
+            }
+            out.push_str(UPPER_HALF);
+        }
+        out.push_str("\x1b[0m\n");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, to_ansi};
+    use crate::graphics::tests::{assert_golden, sample};
+
+    #[test]
+    fn matches_golden_file() {
+        let cells = encode(&sample());
+
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[3][0].1, None);
+        assert_golden("halfblock.golden", &to_ansi(&cells));
+    }
+}
@@ -0,0 +1,59 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::RgbImage;
+
+/// Removes every image kitty is showing.
+pub const DELETE_ALL: &str = "\x1b_Ga=d,d=A,q=2\x1b\\";
+
+/// Payloads have to be split into chunks of at most this many bytes.
+const CHUNK_SIZE: usize = 4096;
+
+/// Encodes raw RGB data with the kitty graphics protocol, scaled into `cols`x`rows` cells.
+/// Images shown before are removed first, and the cursor isn't moved.
+pub fn encode(image: &RgbImage, cols: u16, rows: u16) -> String {
+    let payload = STANDARD.encode(image.as_raw());
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(CHUNK_SIZE).collect();
+    let mut out = String::from(DELETE_ALL);
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        // Only the first chunk carries the image's keys.
+        let keys = match i {
+            0 => format!(
+                "a=T,f=24,s={},v={},c={cols},r={rows},C=1,q=2,m={more}",
+                image.width(),
+                image.height()
+            ),
+            _ => format!("m={more}"),
+        };
+
+        out.push_str(&format!(
+            "\x1b_G{keys};{}\x1b\\",
+            String::from_utf8_lossy(chunk)
+        ));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode;
+    use crate::graphics::tests::{assert_golden, sample};
+
+    #[test]
+    fn matches_golden_file() {
+        assert_golden("kitty.golden", &encode(&sample(), 4, 2));
+    }
+
+    #[test]
+    fn splits_large_payloads() {
+        let image = image::RgbImage::new(64, 64);
+        let out = encode(&image, 8, 4);
+
+        // 64 * 64 * 3 bytes is 16384 base64 characters, so four chunks.
+        assert_eq!(out.matches("\x1b_G").count(), 5);
+        assert_eq!(out.matches("m=1;").count(), 3);
+        assert!(out.ends_with("\x1b\\") && out.contains("m=0;"));
+    }
+}
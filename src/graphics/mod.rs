@@ -0,0 +1,238 @@
+pub mod halfblock;
+pub mod kitty;
+pub mod sixel;
+
+use std::env;
+
+use crossterm::terminal;
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbImage};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use ratatui::widgets::Widget;
+use serde::{Deserialize, Serialize};
+
+/// How images are drawn in the terminal.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphicsProtocol {
+    /// Picks one of the others based on the terminal.
+    #[default]
+    Auto,
+    Kitty,
+    Sixel,
+    /// Unicode half blocks, two pixels per cell. Works in any truecolor terminal.
+    Halfblock,
+}
+
+impl GraphicsProtocol {
+    /// Resolves `Auto` from the environment variables terminals set. Querying the terminal
+    /// itself would race with crossterm's event reader, so this is a best guess.
+    pub fn detect(self) -> Self {
+        if self != Self::Auto {
+            return self;
+        }
+
+        let term = env::var("TERM").unwrap_or_default();
+        let program = env::var("TERM_PROGRAM").unwrap_or_default();
+
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || program == "WezTerm"
+            || program == "ghostty"
+        {
+            Self::Kitty
+        } else if term.contains("sixel")
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || program == "iTerm.app"
+        {
+            Self::Sixel
+        } else {
+            Self::Halfblock
+        }
+    }
+
+    /// Size of a terminal cell in image pixels.
+    pub fn cell_size(self) -> (u32, u32) {
+        match self {
+            Self::Halfblock | Self::Auto => (1, 2),
+            Self::Kitty | Self::Sixel => {
+                let size = terminal::window_size()
+                    .ok()
+                    .filter(|s| s.width > 0 && s.columns > 0);
+
+                match size {
+                    Some(s) => (
+                        (s.width / s.columns).max(1) as u32,
+                        (s.height / s.rows.max(1)).max(1) as u32,
+                    ),
+                    // Common default when the terminal doesn't report its pixel size.
+                    None => (8, 16),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Fit {
+    /// The whole page is visible.
+    #[default]
+    Height,
+    /// The page fills the width and can be scrolled.
+    Width,
+}
+
+/// Scales `image` into a `width`x`height` pixel area and crops it to what's visible after
+/// scrolling down `scroll` pixels. Returns the visible part and whether more is left below.
+pub fn fit(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+    fit: Fit,
+    scroll: u32,
+) -> (RgbImage, bool) {
+    let (w, h) = (image.width().max(1), image.height().max(1));
+
+    let scale = match fit {
+        Fit::Height => f64::min(width as f64 / w as f64, height as f64 / h as f64),
+        Fit::Width => width as f64 / w as f64,
+    };
+
+    let new_w = ((w as f64 * scale).round() as u32).clamp(1, width.max(1));
+    let new_h = ((h as f64 * scale).round() as u32).max(1);
+    let resized = image
+        .resize_exact(new_w, new_h, FilterType::Triangle)
+        .to_rgb8();
+
+    let scroll = scroll.min(new_h.saturating_sub(1));
+    let visible = height.min(new_h - scroll).max(1);
+    let cropped = image::imageops::crop_imm(&resized, 0, scroll, new_w, visible).to_image();
+
+    (cropped, scroll + visible < new_h)
+}
+
+/// An image encoded for the terminal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rendered {
+    /// Escape sequence drawing the whole image from the cursor position.
+    Escape(String),
+    /// Colors of each cell, see [`halfblock::encode`].
+    Cells(halfblock::Cells),
+}
+
+impl Rendered {
+    pub fn encode(protocol: GraphicsProtocol, image: &RgbImage, cols: u16, rows: u16) -> Self {
+        match protocol.detect() {
+            GraphicsProtocol::Kitty => Self::Escape(kitty::encode(image, cols, rows)),
+            GraphicsProtocol::Sixel => Self::Escape(sixel::encode(image)),
+            _ => Self::Cells(halfblock::encode(image)),
+        }
+    }
+}
+
+/// Draws a [`Rendered`] image at the top left of its area.
+pub struct ImageWidget<'a> {
+    rendered: &'a Rendered,
+}
+
+impl<'a> ImageWidget<'a> {
+    pub fn new(rendered: &'a Rendered) -> Self {
+        Self { rendered }
+    }
+}
+
+impl Widget for ImageWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+
+        match self.rendered {
+            Rendered::Escape(sequence) => {
+                // The sequence lives in the first cell; the rest are skipped so ratatui doesn't
+                // draw over the image.
+                for y in area.top()..area.bottom() {
+                    for x in area.left()..area.right() {
+                        buf.get_mut(x, y).reset();
+                        buf.get_mut(x, y).set_skip(true);
+                    }
+                }
+
+                let cell = buf.get_mut(area.x, area.y);
+                cell.set_skip(false);
+                cell.set_symbol(sequence);
+            }
+
+            Rendered::Cells(rows) => {
+                for (y, row) in rows.iter().take(area.height as usize).enumerate() {
+                    for (x, (top, bottom)) in row.iter().take(area.width as usize).enumerate() {
+                        let cell = buf.get_mut(area.x + x as u16, area.y + y as u16);
+                        let bg = bottom.map_or(Color::Reset, |b| Color::Rgb(b[0], b[1], b[2]));
+
+                        cell.set_symbol(halfblock::UPPER_HALF)
+                            .set_fg(Color::Rgb(top[0], top[1], top[2]))
+                            .set_bg(bg);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use image::{DynamicImage, Rgb, RgbImage};
+
+    use super::{Fit, fit};
+
+    /// Small image with distinct colors in every pixel, used by the golden tests.
+    pub fn sample() -> RgbImage {
+        RgbImage::from_fn(4, 7, |x, y| {
+            Rgb([(x * 80) as u8, (y * 40) as u8, 255 - (x * y * 10) as u8])
+        })
+    }
+
+    /// Compares against a golden file in tests/fixtures/graphics. Run with UPDATE_GOLDEN=1 to
+    /// rewrite the files after an intended change.
+    pub fn assert_golden(name: &str, actual: &str) {
+        let path = format!(
+            "{}/tests/fixtures/graphics/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, actual).unwrap();
+        }
+
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            expected == actual,
+            "{name} doesn't match golden file:\n{actual:?}"
+        );
+    }
+
+    #[test]
+    fn fit_height_keeps_the_whole_page() {
+        let page = DynamicImage::ImageRgb8(RgbImage::new(100, 200));
+
+        let (image, more) = fit(&page, 80, 100, Fit::Height, 0);
+        assert_eq!(image.dimensions(), (50, 100));
+        assert!(!more);
+    }
+
+    #[test]
+    fn fit_width_scrolls() {
+        let page = DynamicImage::ImageRgb8(RgbImage::new(100, 200));
+
+        let (image, more) = fit(&page, 50, 40, Fit::Width, 0);
+        assert_eq!(image.dimensions(), (50, 40));
+        assert!(more);
+
+        let (image, more) = fit(&page, 50, 40, Fit::Width, 80);
+        assert_eq!(image.dimensions(), (50, 20));
+        assert!(!more);
+    }
+}
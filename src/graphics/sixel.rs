@@ -0,0 +1,120 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use image::{Rgb, RgbImage};
+
+/// Levels per channel of the fixed palette (a 6x6x6 color cube).
+const LEVELS: u32 = 6;
+
+/// Encodes an image as sixels, quantized to a 216 color palette. Only the colors that are used
+/// get registered.
+pub fn encode(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let indices: Vec<u16> = image.pixels().map(palette_index).collect();
+    let index = |x: u32, y: u32| indices[(y * width + x) as usize];
+
+    let used: BTreeSet<u16> = indices.iter().copied().collect();
+
+    let mut out = format!("\x1bPq\"1;1;{width};{height}");
+    for color in &used {
+        let [r, g, b] = palette_color(*color);
+        let _ = write!(out, "#{color};2;{r};{g};{b}");
+    }
+
+    // Each sixel covers a column of six pixels.
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let colors: BTreeSet<u16> = rows
+            .clone()
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| index(x, y))
+            .collect();
+
+        for (n, color) in colors.iter().enumerate() {
+            if n > 0 {
+                // Back to the start of the band for the next color.
+                out.push('$');
+            }
+            let _ = write!(out, "#{color}");
+
+            let sixels = (0..width).map(|x| {
+                let bits = rows
+                    .clone()
+                    .filter(|&y| index(x, y) == *color)
+                    .fold(0u8, |acc, y| acc | 1 << (y - band));
+                (63 + bits) as char
+            });
+            push_runs(&mut out, sixels);
+        }
+
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Writes sixels using run-length encoding where it's shorter.
+fn push_runs(out: &mut String, sixels: impl Iterator<Item = char>) {
+    let mut run: Option<(char, usize)> = None;
+
+    let flush = |out: &mut String, run: Option<(char, usize)>| match run {
+        Some((c, n)) if n > 3 => {
+            let _ = write!(out, "!{n}{c}");
+        }
+        Some((c, n)) => out.extend(std::iter::repeat_n(c, n)),
+        None => {}
+    };
+
+    for c in sixels {
+        run = match run {
+            Some((prev, n)) if prev == c => Some((prev, n + 1)),
+            _ => {
+                flush(out, run);
+                Some((c, 1))
+            }
+        };
+    }
+
+    flush(out, run);
+}
+
+fn palette_index(pixel: &Rgb<u8>) -> u16 {
+    let level = |c: u8| (c as u32 * (LEVELS - 1) + 127) / 255;
+    (level(pixel[0]) * LEVELS * LEVELS + level(pixel[1]) * LEVELS + level(pixel[2])) as u16
+}
+
+/// Color of a palette entry, in percent as sixel expects.
+fn palette_color(index: u16) -> [u32; 3] {
+    let index = index as u32;
+    let percent = |level: u32| level * 100 / (LEVELS - 1);
+
+    [
+        percent(index / (LEVELS * LEVELS)),
+        percent(index / LEVELS % LEVELS),
+        percent(index % LEVELS),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::encode;
+    use crate::graphics::tests::{assert_golden, sample};
+
+    #[test]
+    fn matches_golden_file() {
+        assert_golden("sixel.golden", &encode(&sample()));
+    }
+
+    #[test]
+    fn compresses_runs() {
+        let image = RgbImage::from_pixel(10, 6, Rgb([255, 255, 255]));
+
+        assert_eq!(
+            encode(&image),
+            "\x1bPq\"1;1;10;6#215;2;100;100;100#215!10~-\x1b\\"
+        );
+    }
+}
@@ -1,9 +1,68 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use std::{fmt, fs, io};
+
+use futures::StreamExt;
+use futures::future::join_all;
 use ratatui::widgets::ListDirection;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::UnboundedSender;
 
-use crate::app::CLIENT;
+use crate::app::NikaAction;
+use crate::keymap::Action;
+use crate::progress::{self, ProgressUpdate};
 use crate::traits::Source;
 
+/// State that every page has to see the same, e.g. the library. Cloning gives another handle to
+/// it.
+pub struct Shared<T>(Arc<Mutex<T>>);
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(Mutex::new(value)))
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        // Every change is saved right away, so a panic can't leave it half done.
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Sends a request, logging where it went, what came back and how long it took.
+pub async fn send(request: RequestBuilder) -> reqwest::Result<Response> {
+    let start = Instant::now();
+    let result = request.send().await;
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+
+    match &result {
+        Ok(response) if response.status().is_success() => {
+            let (url, status) = (response.url().as_str(), response.status().as_u16());
+            tracing::debug!(url, status, elapsed_ms, "response");
+        }
+        Ok(response) => {
+            let (url, status) = (response.url().as_str(), response.status().as_u16());
+            tracing::warn!(url, status, elapsed_ms, "response");
+        }
+        Err(e) => {
+            let url = e.url().map(|u| u.as_str());
+            tracing::warn!(url, elapsed_ms, "request failed: {e}");
+        }
+    }
+    result
+}
+
 pub async fn get_search_response_body(
+    client: &Client,
     query: &str,
     source: &impl Source,
 ) -> reqwest::Result<String> {
@@ -11,7 +70,7 @@ pub async fn get_search_response_body(
     let tmp = query.replace(' ', "+");
 
     let url = format!("{base_url}/search?q={tmp}");
-    CLIENT.get(url).send().await?.text().await
+    send(client.get(url)).await?.text().await
 }
 
 pub fn get_new_selection_index(val: usize, len: usize, direction: ListDirection) -> usize {
@@ -34,11 +93,442 @@ pub fn get_new_selection_index(val: usize, len: usize, direction: ListDirection)
     }
 }
 
-/*
-pub fn get_source(source: &Sources) -> Box<dyn Source> {
-    match source {
-        Sources::Mangapill => Box::new(MangapillSource::new()),
-        Sources::Mangasee => Box::new(MangaseeSource::new()),
+/// Where a list's selection goes for one of the movement actions, or `None` for the others and
+/// empty lists. `height` is how many items fit on screen, for the half page jumps.
+pub fn navigate(selected: usize, len: usize, height: usize, action: Action) -> Option<usize> {
+    let last = len.checked_sub(1)?;
+    let half_page = (height / 2).max(1);
+
+    let index = match action {
+        Action::Up => get_new_selection_index(selected, len, ListDirection::BottomToTop),
+        Action::Down => get_new_selection_index(selected, len, ListDirection::TopToBottom),
+        Action::Top => 0,
+        Action::Bottom => last,
+        Action::HalfPageUp => selected.saturating_sub(half_page),
+        Action::HalfPageDown => (selected + half_page).min(last),
+        _ => return None,
+    };
+    Some(index)
+}
+
+/// Bytes needed to recognize every supported image format.
+const MAGIC_LEN: usize = 12;
+
+/// File extension for an image, from its first bytes or else its Content-Type. Falls back to jpeg,
+/// which is what most sources serve.
+pub fn image_extension(content_type: Option<&str>, bytes: &[u8]) -> &'static str {
+    let at = |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, &[0xFF, 0xD8, 0xFF]) {
+        return "jpeg";
+    }
+    if at(0, b"\x89PNG") {
+        return "png";
+    }
+    if at(0, b"RIFF") && at(8, b"WEBP") {
+        return "webp";
+    }
+    if at(4, b"ftypavif") || at(4, b"ftypavis") {
+        return "avif";
+    }
+
+    let mime = content_type
+        .and_then(|c| c.split(';').next())
+        .map(|c| c.trim().to_ascii_lowercase());
+
+    match mime.as_deref() {
+        Some("image/png") => "png",
+        Some("image/webp") => "webp",
+        Some("image/avif") => "avif",
+        _ => "jpeg",
+    }
+}
+
+/// Whether the built-in reader can decode pages saved as `extension`. AVIF pages are kept as they
+/// are, but only an external viewer can show them.
+pub fn can_decode(extension: &str) -> bool {
+    image::ImageFormat::from_extension(extension).is_some_and(|f| f.reading_enabled())
+}
+
+/// Writes `data` to a file next to `path`, then moves it over `path`, so a crash can't leave it
+/// half written.
+pub fn write_atomically(path: &Path, data: &str) -> io::Result<()> {
+    let mut temporary = path.to_owned().into_os_string();
+    temporary.push(".tmp");
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&temporary, data)?;
+    fs::rename(&temporary, path)
+}
+
+/// Name of the `index`th page (from 0), zero-padded so that sorting by name keeps page order.
+pub fn page_file_name(index: usize, page_count: usize, extension: &str) -> String {
+    let width = page_count.to_string().len().max(3);
+    format!("{:0width$}.{extension}", index + 1)
+}
+
+/// How many times a page is requested before giving up on it.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Wait before retrying a page, doubled after every attempt.
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Pages of a chapter that couldn't be downloaded, even after retrying. The others were saved.
+#[derive(Debug)]
+pub struct MissingPages {
+    /// Where the pages that did arrive are.
+    pub dir: PathBuf,
+    /// Page numbers, starting from 1.
+    pub pages: Vec<usize>,
+    pub total: usize,
+    /// Why the first of them failed.
+    pub reason: String,
+}
+
+impl MissingPages {
+    /// Whether at least one page arrived, so the chapter can still be read.
+    pub fn is_partial(&self) -> bool {
+        self.pages.len() < self.total
+    }
+}
+
+impl fmt::Display for MissingPages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pages: Vec<String> = self.pages.iter().map(usize::to_string).collect();
+        write!(
+            f,
+            "{} of {} pages couldn't be downloaded (pages {}): {}",
+            self.pages.len(),
+            self.total,
+            pages.join(", "),
+            self.reason
+        )
+    }
+}
+
+impl std::error::Error for MissingPages {}
+
+/// Downloads every image in `urls` into `path`, named after their position in `urls`. Pages that
+/// keep failing are reported with [`MissingPages`].
+/// sender is used to report progress, e.g. to the loading screen.
+pub async fn download_images(
+    client: &Client,
+    urls: &[String],
+    path: &Path,
+    referer: &str,
+    sender: Option<UnboundedSender<NikaAction>>,
+) -> anyhow::Result<()> {
+    let page_count = urls.len();
+    progress::report(&sender, ProgressUpdate::PageCount(page_count));
+
+    // images are downloaded concurrently.
+    let results = join_all(urls.iter().enumerate().map(|(index, url)| {
+        let page = PageRequest {
+            client,
+            url,
+            referer,
+            dir: path,
+            index,
+            page_count,
+            sender: &sender,
+        };
+
+        async move {
+            let result = page.download().await;
+            progress::report(page.sender, ProgressUpdate::PageDone);
+            result
+        }
+    }))
+    .await;
+
+    let failed: Vec<(usize, anyhow::Error)> = results
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, r)| r.err().map(|e| (i + 1, e)))
+        .collect();
+
+    tracing::debug!(
+        pages = page_count,
+        failed = failed.len(),
+        "downloaded images"
+    );
+
+    match failed.first() {
+        None => Ok(()),
+        Some((_, reason)) => Err(MissingPages {
+            dir: path.to_owned(),
+            reason: reason.to_string(),
+            pages: failed.iter().map(|(page, _)| *page).collect(),
+            total: page_count,
+        }
+        .into()),
+    }
+}
+
+/// One page of a chapter, see [`download_images`].
+struct PageRequest<'a> {
+    client: &'a Client,
+    url: &'a str,
+    referer: &'a str,
+    dir: &'a Path,
+    /// Position in the chapter, from 0.
+    index: usize,
+    page_count: usize,
+    sender: &'a Option<UnboundedSender<NikaAction>>,
+}
+
+impl PageRequest<'_> {
+    /// Downloads the page, retrying with backoff when the failure might not happen again.
+    async fn download(&self) -> anyhow::Result<()> {
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 1;
+        let mut size_reported = false;
+
+        loop {
+            let mut received = 0;
+            let result = self.try_download(&mut size_reported, &mut received).await;
+
+            if result.is_err() {
+                // Whatever arrived will be downloaded again, or never completes.
+                progress::report(self.sender, ProgressUpdate::Discarded(received));
+            }
+
+            match result {
+                Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => {
+                    tracing::debug!(url = self.url, attempt, "retrying in {delay:?}: {e:#}");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn try_download(
+        &self,
+        size_reported: &mut bool,
+        received: &mut u64,
+    ) -> anyhow::Result<()> {
+        let response = send(self.client.get(self.url).header("Referer", self.referer))
+            .await?
+            .error_for_status()?;
+
+        // Only counted once, however often the page is retried.
+        if !*size_reported {
+            *size_reported = true;
+            progress::report(
+                self.sender,
+                ProgressUpdate::PageSize(response.content_length()),
+            );
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+        let mut stream = response.bytes_stream();
+        let mut count = |bytes: usize| {
+            *received += bytes as u64;
+            progress::report(self.sender, ProgressUpdate::Bytes(bytes as u64));
+        };
+
+        // The file can only be named once the first bytes are in.
+        let mut head = Vec::new();
+        while head.len() < MAGIC_LEN {
+            match stream.next().await {
+                Some(chunk) => {
+                    let chunk = chunk?;
+                    count(chunk.len());
+                    head.extend_from_slice(&chunk);
+                }
+                None => break,
+            }
+        }
+
+        let extension = image_extension(content_type.as_deref(), &head);
+        if !can_decode(extension) {
+            tracing::warn!(
+                page = self.index + 1,
+                extension,
+                "saved a page the built-in reader can't show"
+            );
+        }
+        let path = self
+            .dir
+            .join(page_file_name(self.index, self.page_count, extension));
+
+        let result: anyhow::Result<()> = async {
+            let mut f = File::create(&path).await?;
+            f.write_all(&head).await?;
+
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                count(chunk.len());
+                f.write_all(&chunk).await?;
+            }
+
+            // tokio's File buffers writes, so make sure everything reaches the disk.
+            f.flush().await?;
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            // A retry might pick another extension, so don't leave half a page behind.
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+        result
+    }
+}
+
+/// Whether asking again might help: server errors, rate limits and dropped connections, but not
+/// e.g. a 404 or a full disk.
+fn is_transient(error: &anyhow::Error) -> bool {
+    let Some(error) = error.downcast_ref::<reqwest::Error>() else {
+        return false;
+    };
+
+    match error.status() {
+        Some(status) => {
+            status.is_server_error()
+                || status == StatusCode::TOO_MANY_REQUESTS
+                || status == StatusCode::REQUEST_TIMEOUT
+        }
+        None => error.is_connect() || error.is_timeout() || error.is_body() || error.is_request(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{
+        MissingPages, can_decode, download_images, image_extension, navigate, page_file_name,
+    };
+    use crate::app::CLIENT;
+    use crate::keymap::Action;
+    use crate::test_utils::{MockResponse, MockServer};
+
+    const PNG: &[u8] = &[
+        0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0x0D,
+    ];
+    const WEBP: &[u8] = b"RIFF\x10\0\0\0WEBPVP8 ";
+    const AVIF: &[u8] = b"\0\0\0\x1cftypavif\0\0";
+    const JPEG: &[u8] = &[
+        0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10, b'J', b'F', b'I', b'F', 0, 1,
+    ];
+
+    #[test]
+    fn recognizes_image_formats() {
+        assert_eq!(image_extension(None, PNG), "png");
+        assert_eq!(image_extension(None, WEBP), "webp");
+        assert_eq!(image_extension(None, AVIF), "avif");
+        assert_eq!(image_extension(Some("image/png"), JPEG), "jpeg");
+
+        // Only the header is left to go by.
+        assert_eq!(image_extension(Some("image/webp; q=1"), b"????"), "webp");
+        assert_eq!(image_extension(Some("IMAGE/AVIF"), b""), "avif");
+        assert_eq!(
+            image_extension(Some("application/octet-stream"), b""),
+            "jpeg"
+        );
+
+        assert!(can_decode("jpeg") && can_decode("webp"));
+        assert!(!can_decode("avif"));
+    }
+
+    #[test]
+    fn pads_page_numbers() {
+        assert_eq!(page_file_name(0, 20, "png"), "001.png");
+        assert_eq!(page_file_name(9, 20, "png"), "010.png");
+        assert_eq!(page_file_name(41, 1200, "jpeg"), "0042.jpeg");
+    }
+
+    #[test]
+    fn moves_the_selection_within_the_list() {
+        assert_eq!(navigate(0, 30, 20, Action::Up), Some(0));
+        assert_eq!(navigate(0, 30, 20, Action::Down), Some(1));
+        assert_eq!(navigate(3, 30, 20, Action::HalfPageDown), Some(13));
+        assert_eq!(navigate(25, 30, 20, Action::HalfPageDown), Some(29));
+        assert_eq!(navigate(3, 30, 20, Action::HalfPageUp), Some(0));
+        assert_eq!(navigate(3, 30, 20, Action::Bottom), Some(29));
+        assert_eq!(navigate(3, 30, 20, Action::Select), None);
+        assert_eq!(navigate(0, 0, 20, Action::Down), None);
+    }
+
+    #[tokio::test]
+    async fn names_pages_by_position_and_format() {
+        let server = MockServer::start(vec![
+            ("/1", MockResponse::new(200, "image/jpeg", JPEG)),
+            // Mislabelled, the bytes win.
+            ("/2", MockResponse::new(200, "image/jpeg", PNG)),
+            (
+                "/3",
+                MockResponse::new(200, "application/octet-stream", WEBP),
+            ),
+        ])
+        .await;
+        let urls: Vec<String> = ["/1", "/2", "/3"]
+            .iter()
+            .map(|p| format!("{}{p}", server.url()))
+            .collect();
+
+        let dir = env::temp_dir().join("nika-download-images-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        download_images(&CLIENT, &urls, &dir, &server.url(), None)
+            .await
+            .unwrap();
+
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|f| f.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+
+        assert_eq!(names, ["001.jpeg", "002.png", "003.webp"]);
+        assert_eq!(fs::read(dir.join("003.webp")).unwrap(), WEBP);
+    }
+
+    #[tokio::test]
+    async fn retries_pages_and_reports_the_missing_ones() {
+        let error = || MockResponse::new(500, "text/plain", "oops");
+        let server = MockServer::start_with_sequences(vec![
+            (
+                "/1",
+                vec![error(), error(), MockResponse::new(200, "image/jpeg", JPEG)],
+            ),
+            ("/2", vec![error()]),
+            ("/3", vec![MockResponse::new(404, "text/plain", "gone")]),
+        ])
+        .await;
+        let urls: Vec<String> = ["/1", "/2", "/3"]
+            .iter()
+            .map(|p| format!("{}{p}", server.url()))
+            .collect();
+
+        let dir = env::temp_dir().join("nika-download-retry-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let error = download_images(&CLIENT, &urls, &dir, &server.url(), None)
+            .await
+            .unwrap_err();
+        let missing = error.downcast_ref::<MissingPages>().unwrap();
+
+        assert_eq!(missing.pages, [2, 3]);
+        assert_eq!(missing.total, 3);
+        assert!(missing.is_partial());
+        assert_eq!(fs::read(dir.join("001.jpeg")).unwrap(), JPEG);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // A 404 isn't going to change, unlike a 500.
+        let requests = server.requests();
+        let count = |path: &str| requests.iter().filter(|r| *r == path).count();
+        assert_eq!((count("/1"), count("/2"), count("/3")), (3, 3, 1));
     }
 }
-*/
@@ -0,0 +1,230 @@
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::HISTORY_FILE;
+use crate::models::comic::Chapter;
+use crate::{helpers, paths};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChapterProgress {
+    pub url: String,
+    /// Last page the reader reported, if it can report one.
+    pub last_page: Option<usize>,
+}
+
+/// Reading progress for one comic, keyed like library entries (source name plus comic url).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ComicProgress {
+    pub source_name: String,
+    pub url: String,
+    #[serde(default)]
+    pub chapters: Vec<ChapterProgress>,
+}
+
+impl ComicProgress {
+    pub fn is_read(&self, chapter: &Chapter) -> bool {
+        self.chapter(&chapter.source).is_some()
+    }
+
+    pub fn chapter(&self, url: &str) -> Option<&ChapterProgress> {
+        self.chapters.iter().find(|c| c.url == url)
+    }
+
+    /// Index (into `chapters`) of the chapter to continue with: the first unread one after the
+    /// furthest chapter read so far. Sources list chapters newest first, so reading order is
+    /// back to front.
+    pub fn resume_index(&self, chapters: &[Chapter]) -> Option<usize> {
+        let reading_order = || (0..chapters.len()).rev();
+        let furthest = (0..chapters.len()).find(|&i| self.is_read(&chapters[i]));
+
+        let unread_after = |start: Option<usize>| {
+            reading_order()
+                .skip_while(move |&i| start.is_some_and(|s| i >= s))
+                .find(|&i| !self.is_read(&chapters[i]))
+        };
+
+        unread_after(furthest).or_else(|| unread_after(None))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ReadingHistory {
+    #[serde(default)]
+    comics: Vec<ComicProgress>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl ReadingHistory {
+    /// Loads the history stored in the data dir.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_from(&paths::get().data_file(HISTORY_FILE))
+    }
+
+    /// Starts empty if there's no file yet.
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        let mut history: Self = match fs::read_to_string(path) {
+            Ok(data) => toml::from_str(&data).map_err(|e| {
+                anyhow::anyhow!(
+                    "{} isn't valid, fix it first: {}",
+                    path.display(),
+                    e.message()
+                )
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(anyhow::anyhow!("Couldn't read {}: {e}", path.display())),
+        };
+
+        history.path = Some(path.to_owned());
+        Ok(history)
+    }
+
+    /// Writes the history back to the file it was loaded from. One that wasn't loaded, e.g.
+    /// because the file is broken, refuses to be saved.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Err(io::Error::other(
+                "The reading history couldn't be loaded, so it isn't saved over",
+            ));
+        };
+        let data = toml::to_string(self).map_err(io::Error::other)?;
+        helpers::write_atomically(path, &data)
+    }
+
+    pub fn comic(&self, source_name: &str, url: &str) -> Option<&ComicProgress> {
+        self.comics
+            .iter()
+            .find(|c| c.source_name == source_name && c.url == url)
+    }
+
+    /// Marks a chapter as read, keeping the last page recorded for it (if any).
+    pub fn mark_read(&mut self, source_name: &str, url: &str, chapter: &Chapter) {
+        self.chapter_mut(source_name, url, chapter);
+    }
+
+    pub fn set_last_page(&mut self, source_name: &str, url: &str, chapter: &Chapter, page: usize) {
+        self.chapter_mut(source_name, url, chapter).last_page = Some(page);
+    }
+
+    fn chapter_mut(
+        &mut self,
+        source_name: &str,
+        url: &str,
+        chapter: &Chapter,
+    ) -> &mut ChapterProgress {
+        let comic = match self
+            .comics
+            .iter()
+            .position(|c| c.source_name == source_name && c.url == url)
+        {
+            Some(i) => &mut self.comics[i],
+            None => {
+                self.comics.push(ComicProgress {
+                    source_name: source_name.to_owned(),
+                    url: url.to_owned(),
+                    chapters: Vec::new(),
+                });
+                self.comics.last_mut().unwrap()
+            }
+        };
+
+        match comic.chapters.iter().position(|c| c.url == chapter.source) {
+            Some(i) => &mut comic.chapters[i],
+            None => {
+                comic.chapters.push(ChapterProgress {
+                    url: chapter.source.to_owned(),
+                    last_page: None,
+                });
+                comic.chapters.last_mut().unwrap()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::ReadingHistory;
+    use crate::models::comic::Chapter;
+
+    const COMIC: &str = "https://mangapill.com/manga/2/one-piece";
+
+    /// Newest first, like the sources return them.
+    fn chapters() -> Vec<Chapter> {
+        (1..=5)
+            .rev()
+            .map(|i| Chapter::new(&format!("Chapter {i}"), &format!("{COMIC}/{i}")))
+            .collect()
+    }
+
+    #[test]
+    fn resumes_after_the_furthest_read_chapter() {
+        let chapters = chapters();
+        let mut history = ReadingHistory::default();
+
+        // Read chapter 1, which is listed last.
+        history.mark_read("mangapill", COMIC, &chapters[4]);
+        history.mark_read("mangapill", "https://mangapill.com/manga/3", &chapters[4]);
+        let progress = history.comic("mangapill", COMIC).unwrap();
+        assert_eq!(progress.resume_index(&chapters), Some(3));
+
+        // Skipped chapter 2, read chapter 3.
+        history.mark_read("mangapill", COMIC, &chapters[2]);
+        let progress = history.comic("mangapill", COMIC).unwrap();
+        assert_eq!(progress.resume_index(&chapters), Some(1));
+
+        // Caught up: go back to what was skipped.
+        history.mark_read("mangapill", COMIC, &chapters[1]);
+        history.mark_read("mangapill", COMIC, &chapters[0]);
+        let progress = history.comic("mangapill", COMIC).unwrap();
+        assert_eq!(progress.resume_index(&chapters), Some(3));
+
+        history.mark_read("mangapill", COMIC, &chapters[3]);
+        let progress = history.comic("mangapill", COMIC).unwrap();
+        assert_eq!(progress.resume_index(&chapters), None);
+    }
+
+    #[test]
+    fn keeps_last_page_across_saves() {
+        let path = env::temp_dir()
+            .join("nika-history-test")
+            .join("history.toml");
+        let _ = fs::remove_file(&path);
+        let chapters = chapters();
+
+        let mut history = ReadingHistory::load_from(&path).unwrap();
+        history.set_last_page("mangapill", COMIC, &chapters[0], 7);
+        history.mark_read("mangapill", COMIC, &chapters[0]);
+        history.save().unwrap();
+
+        let loaded = ReadingHistory::load_from(&path).unwrap();
+        let progress = loaded.comic("mangapill", COMIC).unwrap();
+
+        assert!(progress.is_read(&chapters[0]));
+        assert!(!progress.is_read(&chapters[1]));
+        assert_eq!(
+            progress.chapter(&chapters[0].source).unwrap().last_page,
+            Some(7)
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reports_a_file_it_cant_parse() {
+        let path = env::temp_dir()
+            .join("nika-history-invalid-test")
+            .join("history.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "comics = 3").unwrap();
+
+        let error = ReadingHistory::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("isn't valid"), "{error}");
+        assert!(ReadingHistory::default().save().is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+}
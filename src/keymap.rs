@@ -0,0 +1,667 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// Where a key is pressed. The same key can mean different things in different places.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Context {
+    /// Applies everywhere, unless a page binds the key to something else.
+    Global,
+    Home,
+    /// The search page's results.
+    Search,
+    /// Typing into a text field. Unbound keys are typed, so global bindings don't apply.
+    Editing,
+    Comic,
+    Reader,
+    Library,
+    Downloads,
+    Options,
+    Logs,
+    /// Error notifications, over any page. Keys that aren't bound here go to the page, unless
+    /// the details are open.
+    Notification,
+}
+
+impl Context {
+    /// Where keys that aren't bound here are looked up next.
+    fn fallback(self) -> Option<Context> {
+        match self {
+            Context::Global | Context::Editing | Context::Notification => None,
+            _ => Some(Context::Global),
+        }
+    }
+}
+
+/// Something a key can be bound to. Pages ignore the ones that mean nothing to them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    Back,
+    Home,
+    Search,
+    Library,
+    Downloads,
+    Options,
+    /// Opens the log viewer.
+    Logs,
+    Up,
+    Down,
+    Top,
+    Bottom,
+    HalfPageUp,
+    HalfPageDown,
+    Select,
+    /// Stops editing, keeping what was typed.
+    Confirm,
+    Edit,
+    CycleSource,
+    ToggleLibrary,
+    ContinueReading,
+    Mark,
+    DownloadMarked,
+    DownloadUnread,
+    NextPage,
+    PreviousPage,
+    NextChapter,
+    FitWidth,
+    FitHeight,
+    Pause,
+    Cancel,
+    Retry,
+    ClearFinished,
+    Remove,
+    Details,
+    Dismiss,
+}
+
+/// Which bindings to start from. The ones in config.toml replace theirs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    #[default]
+    Default,
+    /// j/k to move, g/G for the top and bottom, Ctrl-d/Ctrl-u for half pages.
+    Vim,
+}
+
+type Bindings = &'static [(Context, Action, &'static [&'static str])];
+
+const DEFAULT: Bindings = &[
+    (Context::Global, Action::Quit, &["q"]),
+    (Context::Global, Action::Back, &["esc"]),
+    (Context::Global, Action::Home, &["h"]),
+    (Context::Global, Action::Search, &["s"]),
+    (Context::Global, Action::Up, &["up"]),
+    (Context::Global, Action::Down, &["down"]),
+    (Context::Global, Action::Top, &["home"]),
+    (Context::Global, Action::Bottom, &["end"]),
+    (Context::Global, Action::HalfPageUp, &["pageup"]),
+    (Context::Global, Action::HalfPageDown, &["pagedown"]),
+    (Context::Global, Action::Select, &["enter"]),
+    (Context::Home, Action::Library, &["l"]),
+    (Context::Home, Action::Downloads, &["d"]),
+    (Context::Home, Action::Options, &["o"]),
+    (Context::Home, Action::Logs, &["L"]),
+    (Context::Search, Action::Edit, &["/"]),
+    (Context::Search, Action::CycleSource, &["s"]),
+    (Context::Search, Action::Logs, &["L"]),
+    (Context::Editing, Action::Back, &["esc"]),
+    (Context::Editing, Action::Confirm, &["enter"]),
+    (Context::Comic, Action::ToggleLibrary, &["a"]),
+    (Context::Comic, Action::ContinueReading, &["c"]),
+    (Context::Comic, Action::Mark, &["space"]),
+    (Context::Comic, Action::DownloadMarked, &["d"]),
+    (Context::Comic, Action::DownloadUnread, &["D"]),
+    (Context::Comic, Action::Downloads, &["g"]),
+    (Context::Comic, Action::NextPage, &["right"]),
+    (Context::Comic, Action::PreviousPage, &["left"]),
+    (Context::Reader, Action::NextPage, &["right", "space", "l"]),
+    (Context::Reader, Action::PreviousPage, &["left", "h"]),
+    (Context::Reader, Action::Up, &["up", "k"]),
+    (Context::Reader, Action::Down, &["down", "j"]),
+    (Context::Reader, Action::FitWidth, &["w"]),
+    (Context::Reader, Action::FitHeight, &["f"]),
+    (Context::Reader, Action::NextChapter, &["n"]),
+    (Context::Library, Action::Remove, &["d"]),
+    (Context::Downloads, Action::Pause, &["p"]),
+    (Context::Downloads, Action::Cancel, &["c"]),
+    (Context::Downloads, Action::Retry, &["r"]),
+    (Context::Downloads, Action::ClearFinished, &["x"]),
+    (Context::Options, Action::Select, &["enter", "space"]),
+    (Context::Notification, Action::Details, &["ctrl-e"]),
+    (Context::Notification, Action::Retry, &["ctrl-r"]),
+    (Context::Notification, Action::Dismiss, &["ctrl-x"]),
+    (Context::Notification, Action::Back, &["esc"]),
+];
+
+/// Replaces bindings in [`DEFAULT`].
+const VIM: Bindings = &[
+    (Context::Global, Action::Up, &["up", "k"]),
+    (Context::Global, Action::Down, &["down", "j"]),
+    (Context::Global, Action::Top, &["home", "g"]),
+    (Context::Global, Action::Bottom, &["end", "G"]),
+    (Context::Global, Action::HalfPageUp, &["pageup", "ctrl-u"]),
+    (
+        Context::Global,
+        Action::HalfPageDown,
+        &["pagedown", "ctrl-d"],
+    ),
+    // g goes to the top.
+    (Context::Comic, Action::Downloads, &["ctrl-g"]),
+];
+
+/// Names of the keys that aren't characters, as written in config.toml.
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("space", KeyCode::Char(' ')),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+const MODIFIER_NAMES: &[(&str, KeyModifiers)] = &[
+    ("ctrl", KeyModifiers::CONTROL),
+    ("alt", KeyModifiers::ALT),
+    ("shift", KeyModifiers::SHIFT),
+];
+
+/// A key with its modifiers, e.g. `ctrl-d`, `G` or `pagedown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Shift is part of the character already, `G` is typed as shift-g.
+    pub fn new(code: KeyCode, mut modifiers: KeyModifiers) -> Self {
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = s;
+
+        // "-" is a key too, as in "ctrl--".
+        while let Some((name, rest)) = key.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+            let (_, modifier) = MODIFIER_NAMES
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .ok_or_else(|| anyhow::anyhow!("Unknown modifier {name:?} in {s:?}"))?;
+            modifiers |= *modifier;
+            key = rest;
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => KEY_NAMES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, code)| *code)
+                .or_else(|| {
+                    let n = key.strip_prefix(['f', 'F'])?.parse().ok()?;
+                    (1..=24).contains(&n).then_some(KeyCode::F(n))
+                })
+                .ok_or_else(|| anyhow::anyhow!("Unknown key {key:?}"))?,
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, modifier) in MODIFIER_NAMES {
+            if self.modifiers.contains(*modifier) {
+                write!(f, "{name}-")?;
+            }
+        }
+
+        match KEY_NAMES.iter().find(|(_, code)| *code == self.code) {
+            Some((name, _)) => f.write_str(name),
+            None => match self.code {
+                KeyCode::Char(c) => write!(f, "{c}"),
+                KeyCode::F(n) => write!(f, "f{n}"),
+                code => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+impl Serialize for KeyChord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The keys bound to an action, written as one or a list of them.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Chords(pub Vec<KeyChord>);
+
+impl<'de> Deserialize<'de> for Chords {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Names {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let names = match Names::deserialize(deserializer)? {
+            Names::One(name) => vec![name],
+            Names::Many(names) => names,
+        };
+        names
+            .iter()
+            .map(|name| name.parse())
+            .collect::<anyhow::Result<_>>()
+            .map(Chords)
+            .map_err(de::Error::custom)
+    }
+}
+
+/// The `[keys]` table of config.toml.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct KeyConfig {
+    pub preset: Preset,
+    /// Tables of actions and their keys, by context, e.g. `[keys.comic]`.
+    #[serde(flatten)]
+    pub bindings: BTreeMap<Context, BTreeMap<Action, Chords>>,
+}
+
+/// What each key does, by context.
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    contexts: HashMap<Context, HashMap<KeyChord, Action>>,
+}
+
+/// An action's keys in a context, and whether they came from config.toml.
+struct Binding {
+    action: Action,
+    chords: Vec<KeyChord>,
+    custom: bool,
+}
+
+impl Keymap {
+    /// Starts from the preset, then applies the user's bindings. Also returns the keys that mean
+    /// two things where at least one of them was set by the user, and which one is used.
+    pub fn new(config: &KeyConfig) -> (Self, Vec<String>) {
+        let preset = match config.preset {
+            Preset::Default => &[][..],
+            Preset::Vim => VIM,
+        };
+
+        let mut tables: BTreeMap<Context, Vec<Binding>> = BTreeMap::new();
+        let mut bind = |context, action, chords: Vec<KeyChord>, custom| {
+            let table = tables.entry(context).or_default();
+            let binding = Binding {
+                action,
+                chords,
+                custom,
+            };
+            match table.iter_mut().find(|b| b.action == action) {
+                Some(existing) => *existing = binding,
+                None => table.push(binding),
+            }
+        };
+
+        for (context, action, names) in DEFAULT.iter().chain(preset) {
+            // The presets are tested, see below.
+            let chords = names.iter().filter_map(|n| n.parse().ok()).collect();
+            bind(*context, *action, chords, false);
+        }
+        for (context, actions) in &config.bindings {
+            for (action, chords) in actions {
+                bind(*context, *action, chords.0.clone(), true);
+            }
+        }
+
+        // Each key's action, and whether the user bound it.
+        let mut resolved: BTreeMap<Context, HashMap<KeyChord, (Action, bool)>> = BTreeMap::new();
+        let mut conflicts = Vec::new();
+
+        for (context, table) in tables {
+            let keys = resolved.entry(context).or_default();
+
+            for binding in table {
+                for chord in binding.chords {
+                    let new = (binding.action, binding.custom);
+                    let Some(&(other, other_custom)) = keys.get(&chord) else {
+                        keys.insert(chord, new);
+                        continue;
+                    };
+                    if other == binding.action {
+                        continue;
+                    }
+
+                    let replace = binding.custom && !other_custom;
+                    if binding.custom || other_custom {
+                        let used = if replace { binding.action } else { other };
+                        conflicts.push(format!(
+                            "keys.{}: {chord} is bound to both {} and {}, {} is used",
+                            name(&context),
+                            name(&other),
+                            name(&binding.action),
+                            name(&used),
+                        ));
+                    }
+                    if replace {
+                        keys.insert(chord, new);
+                    }
+                }
+            }
+        }
+
+        // Pages shadow global keys on purpose, unless the user didn't mean to.
+        for (context, keys) in &resolved {
+            let Some(fallback) = context.fallback() else {
+                continue;
+            };
+            for (chord, (action, custom)) in keys {
+                let Some((other, other_custom)) = resolved[&fallback].get(chord) else {
+                    continue;
+                };
+                if other != action && (*custom || *other_custom) {
+                    conflicts.push(format!(
+                        "keys.{}: {chord} is bound to {} but means {} in keys.{}, {} is used",
+                        name(context),
+                        name(action),
+                        name(other),
+                        name(&fallback),
+                        name(action),
+                    ));
+                }
+            }
+        }
+        conflicts.sort();
+
+        let contexts = resolved
+            .into_iter()
+            .map(|(context, keys)| {
+                let keys = keys.into_iter().map(|(k, (action, _))| (k, action));
+                (context, keys.collect())
+            })
+            .collect();
+
+        (Self { contexts }, conflicts)
+    }
+
+    /// A key for `action` in `context`, for hints. Keys bound in the contexts it falls back to
+    /// count, unless `context` uses them for something else. The first in alphabetical order if
+    /// there are several.
+    pub fn key(&self, context: Context, action: Action) -> Option<KeyChord> {
+        let mut keys = Vec::new();
+        let mut current = Some(context);
+        while let Some(c) = current {
+            if let Some(bindings) = self.contexts.get(&c) {
+                let bound = bindings.iter().filter(|(_, a)| **a == action);
+                keys.extend(bound.map(|(chord, _)| *chord));
+            }
+            current = c.fallback();
+        }
+
+        keys.into_iter()
+            .filter(|chord| self.lookup(context, *chord) == Some(action))
+            .min_by_key(|chord| chord.to_string())
+    }
+
+    /// Hints for the bottom of a page, e.g. "<enter> to open, <esc> back". Actions without a key
+    /// in `context` are left out.
+    pub fn hints(&self, context: Context, hints: &[(Action, &str)]) -> String {
+        hints
+            .iter()
+            .filter_map(|(action, text)| Some(format!("<{}> {text}", self.key(context, *action)?)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// What a key does in `context`, if anything.
+    pub fn action(&self, context: Context, key: KeyEvent) -> Option<Action> {
+        self.lookup(context, KeyChord::from(key))
+    }
+
+    fn lookup(&self, context: Context, chord: KeyChord) -> Option<Action> {
+        let mut context = Some(context);
+
+        while let Some(c) = context {
+            if let Some(action) = self.contexts.get(&c).and_then(|keys| keys.get(&chord)) {
+                return Some(*action);
+            }
+            context = c.fallback();
+        }
+
+        None
+    }
+}
+
+/// How contexts and actions are written in config.toml.
+fn name(value: &impl Serialize) -> String {
+    toml::Value::try_from(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{Action, Chords, Context, DEFAULT, KeyChord, KeyConfig, Keymap, Preset, VIM};
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn char(c: char) -> KeyEvent {
+        key(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn config(preset: Preset, bindings: &[(Context, Action, &str)]) -> KeyConfig {
+        let mut config = KeyConfig {
+            preset,
+            bindings: BTreeMap::new(),
+        };
+        for (context, action, names) in bindings {
+            let chords = names.split(' ').map(|n| n.parse().unwrap()).collect();
+            config
+                .bindings
+                .entry(*context)
+                .or_default()
+                .insert(*action, Chords(chords));
+        }
+        config
+    }
+
+    #[test]
+    fn parses_and_prints_chords() {
+        for name in [
+            "q",
+            "G",
+            "ctrl-d",
+            "alt-enter",
+            "space",
+            "pagedown",
+            "f5",
+            "-",
+            "ctrl--",
+        ] {
+            let chord: KeyChord = name.parse().unwrap();
+            assert_eq!(chord.to_string(), name);
+        }
+
+        assert_eq!(
+            "shift-g".parse::<KeyChord>().unwrap(),
+            KeyChord::new(KeyCode::Char('G'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            "Ctrl-PageUp".parse::<KeyChord>().unwrap().to_string(),
+            "ctrl-pageup"
+        );
+        assert!("hyper-a".parse::<KeyChord>().is_err());
+        assert!("enterr".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn presets_are_valid_and_conflict_free() {
+        for (_, _, names) in DEFAULT.iter().chain(VIM) {
+            for name in *names {
+                assert!(name.parse::<KeyChord>().is_ok(), "{name}");
+            }
+        }
+
+        for preset in [Preset::Default, Preset::Vim] {
+            let (_, conflicts) = Keymap::new(&config(preset, &[]));
+            assert_eq!(conflicts, Vec::<String>::new());
+        }
+    }
+
+    #[test]
+    fn pages_fall_back_to_global_keys() {
+        let (keymap, _) = Keymap::new(&KeyConfig::default());
+
+        assert_eq!(
+            keymap.action(Context::Comic, char('s')),
+            Some(Action::Search)
+        );
+        assert_eq!(
+            keymap.action(Context::Search, char('s')),
+            Some(Action::CycleSource)
+        );
+        assert_eq!(keymap.action(Context::Comic, char('j')), None);
+        // Typed instead.
+        assert_eq!(keymap.action(Context::Editing, char('q')), None);
+        assert_eq!(
+            keymap.action(Context::Editing, key(KeyCode::Esc, KeyModifiers::NONE)),
+            Some(Action::Back)
+        );
+    }
+
+    #[test]
+    fn vim_preset_adds_its_keys() {
+        let (keymap, _) = Keymap::new(&config(Preset::Vim, &[]));
+
+        assert_eq!(
+            keymap.action(Context::Library, char('j')),
+            Some(Action::Down)
+        );
+        assert_eq!(keymap.action(Context::Library, char('k')), Some(Action::Up));
+        assert_eq!(keymap.action(Context::Comic, char('g')), Some(Action::Top));
+        assert_eq!(
+            keymap.action(Context::Comic, key(KeyCode::Char('g'), KeyModifiers::SHIFT)),
+            Some(Action::Bottom)
+        );
+        assert_eq!(
+            keymap.action(
+                Context::Downloads,
+                key(KeyCode::Char('d'), KeyModifiers::CONTROL)
+            ),
+            Some(Action::HalfPageDown)
+        );
+        assert_eq!(
+            keymap.action(Context::Downloads, key(KeyCode::Up, KeyModifiers::NONE)),
+            Some(Action::Up)
+        );
+    }
+
+    #[test]
+    fn hints_show_the_keys_that_work_on_the_page() {
+        let (keymap, _) = Keymap::new(&config(Preset::Vim, &[]));
+
+        assert_eq!(
+            keymap.hints(
+                Context::Comic,
+                &[(Action::Downloads, "downloads"), (Action::Home, "for home")]
+            ),
+            "<ctrl-g> downloads, <h> for home"
+        );
+        // s cycles sources there instead.
+        assert_eq!(keymap.key(Context::Search, Action::Search), None);
+        assert_eq!(
+            keymap
+                .key(Context::Library, Action::Up)
+                .unwrap()
+                .to_string(),
+            "k"
+        );
+    }
+
+    #[test]
+    fn user_bindings_replace_the_preset_and_report_conflicts() {
+        let (keymap, conflicts) = Keymap::new(&config(
+            Preset::Default,
+            &[
+                (Context::Comic, Action::ToggleLibrary, "d"),
+                (Context::Global, Action::Quit, "x ctrl-c"),
+            ],
+        ));
+
+        assert_eq!(
+            keymap.action(Context::Comic, char('d')),
+            Some(Action::ToggleLibrary)
+        );
+        // Replaced, not added to.
+        assert_eq!(keymap.action(Context::Comic, char('a')), None);
+        assert_eq!(keymap.action(Context::Home, char('q')), None);
+        assert_eq!(
+            keymap.action(
+                Context::Home,
+                key(KeyCode::Char('c'), KeyModifiers::CONTROL)
+            ),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(Context::Downloads, char('x')),
+            Some(Action::ClearFinished)
+        );
+
+        assert_eq!(
+            conflicts,
+            [
+                "keys.comic: d is bound to both toggle_library and download_marked, \
+                 toggle_library is used",
+                "keys.downloads: x is bound to clear_finished but means quit in keys.global, \
+                 clear_finished is used",
+            ]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::LIBRARY_FILE;
+use crate::models::comic::{Comic, ComicInfo, ComicType};
+use crate::{helpers, paths};
+
+/// A comic the user follows. Entries are identified by the source's name plus the comic's url,
+/// which is enough to find the comic again in a later session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LibraryEntry {
+    pub name: String,
+    pub url: String,
+    pub source_name: String,
+    #[serde(default)]
+    pub comic_type: ComicType,
+    #[serde(default)]
+    pub info: ComicInfo,
+}
+
+impl LibraryEntry {
+    pub fn new(comic: &Comic, source_name: &str, info: &ComicInfo) -> Self {
+        Self {
+            name: comic.name.to_owned(),
+            url: comic.source.to_owned(),
+            source_name: source_name.to_owned(),
+            comic_type: comic.comic_type.clone(),
+            info: info.clone(),
+        }
+    }
+
+    /// Chapters aren't stored, they're fetched again when the comic is opened.
+    pub fn comic(&self) -> Comic {
+        Comic::new(&self.name, &self.url, self.comic_type.clone(), Vec::new())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Library {
+    #[serde(default)]
+    comics: Vec<LibraryEntry>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Library {
+    /// Loads the library stored in the data dir.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_from(&paths::get().data_file(LIBRARY_FILE))
+    }
+
+    /// Starts empty if there's no file yet.
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        let mut library: Self = match fs::read_to_string(path) {
+            Ok(data) => toml::from_str(&data).map_err(|e| {
+                anyhow::anyhow!(
+                    "{} isn't valid, fix it first: {}",
+                    path.display(),
+                    e.message()
+                )
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(anyhow::anyhow!("Couldn't read {}: {e}", path.display())),
+        };
+
+        library.path = Some(path.to_owned());
+        Ok(library)
+    }
+
+    /// Writes the library back to the file it was loaded from. One that wasn't loaded, e.g.
+    /// because the file is broken, refuses to be saved.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Err(io::Error::other(
+                "The library couldn't be loaded, so it isn't saved over",
+            ));
+        };
+        let data = toml::to_string(self).map_err(io::Error::other)?;
+        helpers::write_atomically(path, &data)
+    }
+
+    pub fn entries(&self) -> &[LibraryEntry] {
+        &self.comics
+    }
+
+    pub fn contains(&self, source_name: &str, url: &str) -> bool {
+        self.position(source_name, url).is_some()
+    }
+
+    /// Adds the entry, replacing any older one for the same comic.
+    pub fn add(&mut self, entry: LibraryEntry) {
+        match self.position(&entry.source_name, &entry.url) {
+            Some(i) => self.comics[i] = entry,
+            None => self.comics.push(entry),
+        }
+    }
+
+    pub fn remove(&mut self, source_name: &str, url: &str) -> Option<LibraryEntry> {
+        self.position(source_name, url)
+            .map(|i| self.comics.remove(i))
+    }
+
+    /// Adds the entry if it isn't saved yet, removes it otherwise. Returns whether the comic is
+    /// now in the library.
+    pub fn toggle(&mut self, entry: LibraryEntry) -> bool {
+        if self.remove(&entry.source_name, &entry.url).is_some() {
+            false
+        } else {
+            self.add(entry);
+            true
+        }
+    }
+
+    fn position(&self, source_name: &str, url: &str) -> Option<usize> {
+        self.comics
+            .iter()
+            .position(|e| e.source_name == source_name && e.url == url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{Library, LibraryEntry};
+    use crate::models::comic::{Comic, ComicInfo, ComicType};
+
+    fn entry(source_name: &str, url: &str) -> LibraryEntry {
+        let comic = Comic::new("One Piece", url, ComicType::Manga, Vec::new());
+        let info = ComicInfo::new("1997", "publishing", vec!["Action".into()]);
+        LibraryEntry::new(&comic, source_name, &info)
+    }
+
+    #[test]
+    fn entries_are_keyed_by_source_and_url() {
+        let mut library = Library::default();
+
+        assert!(library.toggle(entry(
+            "mangapill",
+            "https://mangapill.com/manga/2/one-piece"
+        )));
+        assert!(library.toggle(entry("mangadex", "https://mangapill.com/manga/2/one-piece")));
+        library.add(entry(
+            "mangapill",
+            "https://mangapill.com/manga/2/one-piece",
+        ));
+
+        assert_eq!(library.entries().len(), 2);
+        assert!(!library.toggle(entry("mangadex", "https://mangapill.com/manga/2/one-piece")));
+        assert!(!library.contains("mangadex", "https://mangapill.com/manga/2/one-piece"));
+        assert!(library.contains("mangapill", "https://mangapill.com/manga/2/one-piece"));
+    }
+
+    #[test]
+    fn survives_a_round_trip() {
+        let path = env::temp_dir()
+            .join("nika-library-test")
+            .join("library.toml");
+        let _ = fs::remove_file(&path);
+
+        let mut library = Library::load_from(&path).unwrap();
+        assert!(library.entries().is_empty());
+
+        library.add(entry(
+            "mangapill",
+            "https://mangapill.com/manga/2/one-piece",
+        ));
+        library.save().unwrap();
+
+        let loaded = Library::load_from(&path).unwrap();
+        assert_eq!(loaded.entries(), library.entries());
+        assert_eq!(
+            loaded.entries()[0].comic(),
+            Comic::new(
+                "One Piece",
+                "https://mangapill.com/manga/2/one-piece",
+                ComicType::Manga,
+                Vec::new()
+            )
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn never_saves_over_a_file_it_couldnt_read() {
+        let path = env::temp_dir()
+            .join("nika-library-invalid-test")
+            .join("library.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "[[comics]\nname = ").unwrap();
+
+        let error = Library::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("isn't valid"), "{error}");
+
+        assert!(Library::default().save().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[[comics]\nname = ");
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Registry, fmt, reload};
+
+/// Replaces the configured level, e.g. `NIKA_LOG=debug` or `NIKA_LOG=nika_tui::downloads=trace`.
+pub const LOG_ENV: &str = "NIKA_LOG";
+
+/// Log files are named `nika-tui.<date>.log`, one per day.
+const PREFIX: &str = "nika-tui";
+const SUFFIX: &str = "log";
+
+/// Days of logs kept. Older files are deleted.
+const MAX_FILES: usize = 7;
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// How much goes into the log file.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// Filter directives for the level. Only nika-tui's own events go below warnings, the
+    /// libraries it uses are too chatty.
+    fn directives(self) -> String {
+        let level = match self {
+            LogLevel::Off => return String::from("off"),
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        };
+        format!("warn,nika_tui={level}")
+    }
+}
+
+/// Starts logging to a file in `dir`, a new one every day. [`LOG_ENV`] wins over `level` when
+/// it's set. Events are written in the background until the returned guard is dropped.
+pub fn init(dir: &Path, level: LogLevel) -> anyhow::Result<WorkerGuard> {
+    // The appender complains on stderr while looking for old files to delete otherwise.
+    fs::create_dir_all(dir)?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(PREFIX)
+        .filename_suffix(SUFFIX)
+        .max_log_files(MAX_FILES)
+        .build(dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let from_env = std::env::var(LOG_ENV).ok().map(EnvFilter::try_new);
+    let filter = match &from_env {
+        Some(Ok(filter)) => filter.clone(),
+        _ => EnvFilter::new(level.directives()),
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(writer).with_ansi(false))
+        .try_init()?;
+    let _ = FILTER.set(handle);
+
+    if let Some(Err(e)) = from_env {
+        tracing::warn!("{LOG_ENV} isn't a valid filter, using {level:?}: {e}");
+    }
+    Ok(guard)
+}
+
+/// Applies a level from a changed config, unless [`LOG_ENV`] is set.
+pub fn set_level(level: LogLevel) {
+    if std::env::var_os(LOG_ENV).is_some() {
+        return;
+    }
+    if let Some(handle) = FILTER.get() {
+        let _ = handle.reload(EnvFilter::new(level.directives()));
+    }
+}
+
+/// The log file being written to, which is the newest one in `dir`.
+pub fn current_file(dir: &Path) -> Option<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with(&format!("{PREFIX}.")) && name.ends_with(&format!(".{SUFFIX}"))
+        })
+        .collect();
+
+    // The dates in the names sort in order.
+    files.sort();
+    files.pop()
+}
+
+/// The whole lines in the last `max_bytes` of a file.
+pub fn tail(path: &Path, max_bytes: u64) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    // One byte early, to tell whether the first line starts right there.
+    let start = len.saturating_sub(max_bytes + 1);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    if start > 0 {
+        let cut = bytes
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(bytes.len(), |i| i + 1);
+        bytes.drain(..cut);
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    Ok(text.lines().map(str::to_owned).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{LogLevel, current_file, tail};
+
+    #[test]
+    fn only_nika_goes_below_warnings() {
+        assert_eq!(LogLevel::Debug.directives(), "warn,nika_tui=debug");
+        assert_eq!(LogLevel::Error.directives(), "warn,nika_tui=error");
+        assert_eq!(LogLevel::Off.directives(), "off");
+    }
+
+    #[test]
+    fn reads_the_end_of_the_newest_file() {
+        let dir = env::temp_dir().join("nika-logging-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("nika-tui.2026-10-17.log"), "old\n").unwrap();
+        fs::write(
+            dir.join("nika-tui.2026-10-18.log"),
+            "first\nsecond\nthird\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not a log\n").unwrap();
+
+        let path = current_file(&dir).unwrap();
+        assert_eq!(path, dir.join("nika-tui.2026-10-18.log"));
+
+        assert_eq!(tail(&path, 1024).unwrap(), ["first", "second", "third"]);
+        // "first\n" doesn't fit, and half of "second" isn't kept.
+        assert_eq!(tail(&path, 9).unwrap(), ["third"]);
+        assert_eq!(tail(&path, 13).unwrap(), ["second", "third"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(current_file(&dir), None);
+    }
+}
@@ -1,20 +1,98 @@
-use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::{env, io};
 
 use app::App;
+use clap::Parser;
+use cli::Command;
 use config::Config;
+use constants::{APP_DIR, HISTORY_FILE, LIBRARY_FILE};
+use models::sources::registry::SourceRegistry;
+use paths::Paths;
 
 mod app;
+pub mod cbz;
+pub mod cli;
 pub mod components;
 pub mod config;
 pub mod constants;
+pub mod downloads;
+pub mod graphics;
 pub mod helpers;
+pub mod history;
+pub mod keymap;
+pub mod library;
+pub mod logging;
 pub mod models;
+pub mod operation;
+pub mod paths;
+pub mod progress;
+pub mod theme;
 pub mod traits;
+#[cfg(test)]
+mod test_utils;
 mod tui;
+pub mod viewer;
+
+/// A TUI for reading manga and comics. The commands print what they find instead, for scripts.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Config file to use instead of $XDG_CONFIG_HOME/nika-tui/config.toml.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Where the library, history and downloads go instead of $XDG_DATA_HOME/nika-tui.
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+}
 
 #[tokio::main]
-async fn main() -> io::Result<()> {
-    let config = Config::get_or_default();
-    let mut app = App::new(config);
-    app.run().await
+async fn main() -> io::Result<ExitCode> {
+    let cli = Cli::parse();
+    // A run with its own paths, e.g. to try something out, leaves the usual files where they are.
+    let own_paths = cli.config.is_some() || cli.data_dir.is_some();
+    let paths = Paths::resolve(cli.config, cli.data_dir, |var| env::var_os(var));
+    // Both used to be in ~/.config/nika-tui, whatever XDG_CONFIG_HOME said.
+    if let Some(home) = env::var_os("HOME").filter(|_| !own_paths) {
+        let old_dir = Path::new(&home).join(".config").join(APP_DIR);
+        if let Err(e) = paths.migrate(&old_dir, &[LIBRARY_FILE, HISTORY_FILE]) {
+            let dir = paths.data_dir.display();
+            eprintln!("Couldn't move the library and history to {dir}: {e}");
+        }
+    }
+    paths::init(paths);
+
+    let (config, errors) = Config::load();
+    // Kept until the end, so the last events reach the file.
+    let _guard = match logging::init(&paths::get().state_dir, config.log_level()) {
+        Ok(guard) => Some(guard),
+        Err(e) => {
+            eprintln!("Couldn't start logging: {e}");
+            None
+        }
+    };
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "starting");
+
+    if let Some(command) = cli.command {
+        let (sources, source_errors) =
+            SourceRegistry::new(config.sources(), &paths::get().sources_dir());
+        for error in errors.iter().chain(&source_errors) {
+            eprintln!("Warning: {error}");
+        }
+        let mut out = io::stdout().lock();
+        return Ok(match cli::run(command, &config, &sources, &mut out).await {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("Error: {e:#}");
+                ExitCode::FAILURE
+            }
+        });
+    }
+
+    let mut app = App::new(config, errors);
+    app.run().await?;
+    Ok(ExitCode::SUCCESS)
 }
\ No newline at end of file
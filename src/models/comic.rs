@@ -1,11 +1,13 @@
-#[derive(Debug, Clone, Default)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub enum ComicType {
     #[default]
     Manga,
     Western,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Comic {
     pub name: String,
     pub source: String,
@@ -13,7 +15,7 @@ pub struct Comic {
     pub chapters: Vec<Chapter>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Chapter {
     pub name: String,
     pub source: String,
@@ -28,7 +30,7 @@ impl Chapter {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ComicInfo {
     // might change later.
     pub date: String,
@@ -0,0 +1,694 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::{fmt, fs};
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use soup::{NodeExt, QueryBuilderExt, Soup};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::app::{CLIENT, NikaAction};
+use crate::helpers;
+use crate::models::comic::{Chapter, Comic, ComicInfo, ComicType};
+use crate::models::sources::registry::Capability;
+use crate::progress::{self, Phase, ProgressUpdate};
+use crate::traits::Source;
+
+/// The definitions shipped with nika-tui.
+const MANGAPILL: &str = include_str!("definitions/mangapill.toml");
+
+/// Matches elements by tag, classes and an attribute, e.g. `a.border-border` or `img[data-src]`.
+/// Not full CSS, so classes like `lg:grid-cols-5` need no escaping.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Selector {
+    text: String,
+    tag: Option<String>,
+    classes: Vec<String>,
+    attr: Option<String>,
+}
+
+impl Selector {
+    /// The matching elements under `node`, and `node` itself, in document order.
+    fn all(&self, node: &impl QueryBuilderExt) -> Vec<impl NodeExt + QueryBuilderExt> {
+        node.tag(true)
+            .find_all()
+            .filter(|n| self.matches(n))
+            .collect()
+    }
+
+    fn first(&self, node: &impl QueryBuilderExt) -> Option<impl NodeExt + QueryBuilderExt> {
+        node.tag(true).find_all().find(|n| self.matches(n))
+    }
+
+    fn matches(&self, node: &impl NodeExt) -> bool {
+        let classes = node.get("class").unwrap_or_default();
+        self.tag.as_ref().is_none_or(|tag| node.name() == tag)
+            && self
+                .classes
+                .iter()
+                .all(|class| classes.split_whitespace().any(|c| c == class))
+            && self
+                .attr
+                .as_ref()
+                .is_none_or(|attr| node.get(attr).is_some())
+    }
+}
+
+impl FromStr for Selector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        anyhow::ensure!(!s.trim().is_empty(), "a selector can't be empty");
+        anyhow::ensure!(
+            !s.contains(char::is_whitespace),
+            "{s:?} has spaces, selectors can't look inside other elements"
+        );
+
+        let (rest, attr) = match s.split_once('[') {
+            Some((rest, attr)) => {
+                let attr = attr
+                    .strip_suffix(']')
+                    .filter(|a| !a.is_empty() && !a.contains(['[', ']', '=']))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("{s:?} should end in an attribute like [href]")
+                    })?;
+                (rest, Some(attr.to_owned()))
+            }
+            None => (s, None),
+        };
+
+        let mut parts = rest.split('.');
+        let tag = parts.next().filter(|t| !t.is_empty()).map(str::to_owned);
+        let classes: Vec<String> = parts.map(str::to_owned).collect();
+        anyhow::ensure!(
+            classes.iter().all(|c| !c.is_empty()),
+            "{s:?} has an empty class"
+        );
+
+        Ok(Self {
+            text: s.to_owned(),
+            tag,
+            classes,
+            attr,
+        })
+    }
+}
+
+impl TryFrom<String> for Selector {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> anyhow::Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+fn href() -> String {
+    String::from("href")
+}
+
+fn src() -> String {
+    String::from("src")
+}
+
+fn base_url() -> String {
+    String::from("{base_url}")
+}
+
+/// How to scrape a site, read from a TOML file. See `definitions/mangapill.toml` for one with
+/// every field explained. Sections left out are things the source can't do.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SourceDefinition {
+    /// What the source is called in the config, library and download folders.
+    pub id: String,
+    pub base_url: String,
+    pub search: Option<SearchDefinition>,
+    pub chapters: Option<ChaptersDefinition>,
+    pub info: Option<InfoDefinition>,
+    pub pages: Option<PagesDefinition>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SearchDefinition {
+    /// With `{base_url}` and `{query}` filled in.
+    pub url: String,
+    /// The first match holds the results, one per child element.
+    pub results: Selector,
+    /// In a result, the element whose text is the comic's name.
+    pub name: Selector,
+    /// In a result, the element linking to the comic.
+    pub link: Selector,
+    #[serde(default = "href")]
+    pub link_attr: String,
+    /// Keeps only the results whose text has the query in it.
+    #[serde(default)]
+    pub match_query: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ChaptersDefinition {
+    /// On the comic's page, one per chapter. Its text is the chapter's name.
+    pub link: Selector,
+    #[serde(default = "href")]
+    pub link_attr: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct InfoDefinition {
+    /// On the comic's page, the first match holds the fields.
+    pub fields: Selector,
+    /// The fields, counted from the first match in `fields`, which may be that element itself.
+    pub field: Selector,
+    /// Which field is the status.
+    pub status: usize,
+    /// Which field is the date.
+    pub date: usize,
+    pub genres: Option<GenresDefinition>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GenresDefinition {
+    pub container: Selector,
+    /// Which of the matches of `container` holds the genres.
+    #[serde(default)]
+    pub index: usize,
+    /// One per genre, named after its text.
+    pub item: Selector,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PagesDefinition {
+    /// On the chapter's page, one per page in reading order.
+    pub image: Selector,
+    #[serde(default = "src")]
+    pub attr: String,
+    /// Sent with every request for the chapter, with `{base_url}` filled in.
+    #[serde(default = "base_url")]
+    pub referer: String,
+}
+
+impl SourceDefinition {
+    pub fn mangapill() -> Self {
+        Self::parse(MANGAPILL).expect("the shipped definition is valid")
+    }
+
+    pub fn parse(data: &str) -> anyhow::Result<Self> {
+        let mut definition: Self = toml::from_str(data).map_err(|e| {
+            // The error's own text quotes the file over several lines.
+            let line = e
+                .span()
+                .map_or(1, |span| data[..span.start].matches('\n').count() + 1);
+            anyhow::anyhow!("line {line}: {}", e.message())
+        })?;
+        anyhow::ensure!(!definition.id.is_empty(), "id can't be empty");
+        definition.base_url = definition.base_url.trim_end_matches('/').to_owned();
+        Ok(definition)
+    }
+
+    /// Points the definition at another server, e.g. a local stand-in.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        let sections = [
+            (Capability::Search, self.search.is_some()),
+            (Capability::Chapters, self.chapters.is_some()),
+            (Capability::Info, self.info.is_some()),
+            (Capability::Download, self.pages.is_some()),
+        ];
+        sections
+            .into_iter()
+            .filter_map(|(capability, defined)| defined.then_some(capability))
+            .collect()
+    }
+}
+
+/// Reads every `*.toml` definition in `dir`, in name order. Also returns what was wrong with the
+/// ones left out.
+pub fn load_definitions(dir: &Path) -> (Vec<SourceDefinition>, Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+
+    let mut definitions = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        let parsed = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| SourceDefinition::parse(&data));
+        match parsed {
+            Ok(definition) => definitions.push(definition),
+            Err(e) => errors.push(format!("Source {} isn't valid: {e:#}", path.display())),
+        }
+    }
+
+    (definitions, errors)
+}
+
+/// A source scraped as its [`SourceDefinition`] says, so sites can be added or fixed without
+/// recompiling.
+pub struct ConfigurableHtmlSource {
+    definition: SourceDefinition,
+    capabilities: Vec<Capability>,
+    client: Client,
+}
+
+impl ConfigurableHtmlSource {
+    pub fn new(definition: SourceDefinition) -> Self {
+        Self::with_client(definition, CLIENT.clone())
+    }
+
+    pub fn with_client(definition: SourceDefinition, client: Client) -> Self {
+        Self {
+            capabilities: definition.capabilities(),
+            definition,
+            client,
+        }
+    }
+
+    /// Fills in `{base_url}`.
+    fn template(&self, template: &str) -> String {
+        template.replace("{base_url}", &self.definition.base_url)
+    }
+
+    /// Links on the site are often relative to it.
+    fn absolute(&self, url: &str) -> String {
+        if url.contains("://") {
+            url.to_owned()
+        } else if url.starts_with("//") {
+            format!("https:{url}")
+        } else {
+            let slash = if url.starts_with('/') { "" } else { "/" };
+            format!("{}{slash}{url}", self.definition.base_url)
+        }
+    }
+
+    /// An error page counts as failing, rather than as a page with nothing on it.
+    async fn page(&self, url: &str) -> reqwest::Result<String> {
+        let response = helpers::send(self.client.get(url)).await?;
+        response.error_for_status()?.text().await
+    }
+}
+
+/// Spaces become `+`, and everything else that isn't safe in a query is percent-encoded.
+fn encode_query(query: &str) -> String {
+    let mut encoded = String::with_capacity(query.len());
+    for byte in query.bytes() {
+        match byte {
+            b' ' => encoded.push('+'),
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[async_trait]
+impl Source for ConfigurableHtmlSource {
+    #[tracing::instrument(skip_all, fields(source = self.definition.id, query = query))]
+    async fn search(&self, query: &str) -> reqwest::Result<Vec<Comic>> {
+        let Some(search) = &self.definition.search else {
+            return Ok(Vec::new());
+        };
+
+        let url = self
+            .template(&search.url)
+            .replace("{query}", &encode_query(query));
+        let body = self.page(&url).await?;
+        let root = Soup::new(&body);
+
+        let Some(results) = search.results.first(&root) else {
+            // Couldn't find anything. Also what happens when the page's layout changes.
+            tracing::warn!(bytes = body.len(), selector = %search.results, "no results in the page");
+            return Ok(Vec::new());
+        };
+
+        let query = query.to_lowercase();
+        let comics: Vec<Comic> = results
+            .children()
+            .filter(|result| result.is_element())
+            .filter(|result| {
+                !search.match_query || result.display().to_lowercase().contains(&query)
+            })
+            .filter_map(|result| {
+                let name = search.name.first(&result)?.text();
+                let href = search.link.first(&result)?.get(&search.link_attr)?;
+                Some(Comic::new(
+                    name.trim(),
+                    &self.absolute(&href),
+                    ComicType::Manga,
+                    Vec::new(),
+                ))
+            })
+            .collect();
+
+        tracing::info!(results = comics.len(), "searched");
+        Ok(comics)
+    }
+
+    #[tracing::instrument(skip_all, fields(source = self.definition.id, url = %comic.source))]
+    async fn get_chapters(&self, comic: &Comic) -> reqwest::Result<Vec<Chapter>> {
+        let Some(definition) = &self.definition.chapters else {
+            return Ok(Vec::new());
+        };
+
+        let body = self.page(&comic.source).await?;
+        let root = Soup::new(&body);
+
+        let chapters: Vec<Chapter> = definition
+            .link
+            .all(&root)
+            .into_iter()
+            .filter_map(|link| {
+                let href = link.get(&definition.link_attr)?;
+                Some(Chapter::new(link.text().trim(), &self.absolute(&href)))
+            })
+            .collect();
+
+        tracing::info!(chapters = chapters.len(), "listed chapters");
+        Ok(chapters)
+    }
+
+    #[tracing::instrument(skip_all, fields(source = self.definition.id, url = %comic.source))]
+    async fn get_info(&self, comic: &Comic) -> reqwest::Result<Option<ComicInfo>> {
+        let Some(definition) = &self.definition.info else {
+            return Ok(None);
+        };
+
+        let body = self.page(&comic.source).await?;
+        let root = Soup::new(&body);
+
+        let values = match definition.fields.first(&root) {
+            Some(container) => definition.field.all(&container),
+            None => Vec::new(),
+        };
+        if values.is_empty() {
+            tracing::warn!(selector = %definition.fields, "no info in the page");
+            return Ok(None);
+        }
+        let value = |index: usize| values.get(index).map(|v| v.text()).unwrap_or_default();
+
+        let genres: Vec<String> = definition
+            .genres
+            .as_ref()
+            .and_then(|genres| {
+                let container = genres.container.all(&root).into_iter().nth(genres.index)?;
+                Some(
+                    genres
+                        .item
+                        .all(&container)
+                        .iter()
+                        .map(|g| g.text())
+                        .collect(),
+                )
+            })
+            .unwrap_or_default();
+        tracing::info!(genres = genres.len(), "read info");
+
+        Ok(Some(ComicInfo {
+            status: value(definition.status),
+            date: value(definition.date),
+            genres,
+        }))
+    }
+
+    fn base_url(&self) -> &str {
+        &self.definition.base_url
+    }
+
+    fn name(&self) -> &str {
+        &self.definition.id
+    }
+
+    fn capabilities(&self) -> &[Capability] {
+        &self.capabilities
+    }
+
+    /// sender is used to update progress on loading screen.
+    #[tracing::instrument(skip_all, fields(source = self.definition.id, url = %chapter.source))]
+    async fn download_chapter(
+        &self,
+        chapter: &Chapter,
+        dir: &Path,
+        sender: Option<UnboundedSender<NikaAction>>,
+    ) -> anyhow::Result<()> {
+        let Some(definition) = &self.definition.pages else {
+            anyhow::bail!("{} can't download chapters", self.definition.id);
+        };
+
+        progress::report(&sender, ProgressUpdate::Phase(Phase::FetchingChapter));
+        let referer = self.template(&definition.referer);
+        let req = helpers::send(self.client.get(&chapter.source).header("Referer", &referer))
+            .await?
+            .error_for_status()?;
+
+        let body = req.text().await?;
+        progress::report(&sender, ProgressUpdate::Phase(Phase::ResolvingImages));
+
+        // Has to be inside a code block to make this function Send (soup isn't Send).
+        let urls: Vec<String> = {
+            let root = Soup::new(&body);
+            definition
+                .image
+                .all(&root)
+                .into_iter()
+                .filter_map(|image| image.get(&definition.attr))
+                .map(|url| self.absolute(&url))
+                .collect()
+        };
+        tracing::info!(images = urls.len(), "found pages");
+
+        helpers::download_images(&self.client, &urls, dir, &referer, sender).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{ConfigurableHtmlSource, Selector, SourceDefinition, load_definitions};
+    use crate::app::CLIENT;
+    use crate::models::comic::{Chapter, Comic, ComicInfo, ComicType};
+    use crate::models::sources::registry::Capability;
+    use crate::test_utils::{self, MockResponse, MockServer};
+    use crate::traits::Source;
+
+    fn mangapill(server: &MockServer) -> ConfigurableHtmlSource {
+        let definition = SourceDefinition::mangapill().with_base_url(&server.url());
+        ConfigurableHtmlSource::with_client(definition, CLIENT.clone())
+    }
+
+    fn comic(server: &MockServer) -> Comic {
+        let source = format!("{}/manga/2/one-piece", server.url());
+        Comic::new("One Piece", &source, ComicType::Manga, Vec::new())
+    }
+
+    #[test]
+    fn parses_selectors() {
+        let selector: Selector = "a.border-border.p-1[href]".parse().unwrap();
+        assert_eq!(selector.tag.as_deref(), Some("a"));
+        assert_eq!(selector.classes, ["border-border", "p-1"]);
+        assert_eq!(selector.attr.as_deref(), Some("href"));
+
+        let selector: Selector = ".lg:grid-cols-5".parse().unwrap();
+        assert_eq!(selector.tag, None);
+        assert_eq!(selector.classes, ["lg:grid-cols-5"]);
+
+        for invalid in ["", "div a", "a..b", "img[data-src", "a[]"] {
+            assert!(invalid.parse::<Selector>().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = test_utils::mangapill_server().await;
+        let source = mangapill(&server);
+
+        let results = source.search("One Piece").await.unwrap();
+
+        assert_eq!(
+            results,
+            [
+                comic(&server),
+                Comic::new(
+                    "One Piece Party",
+                    &format!("{}/manga/2262/one-piece-party", server.url()),
+                    ComicType::Manga,
+                    Vec::new()
+                ),
+            ]
+        );
+        assert_eq!(server.requests(), ["/search?q=One+Piece"]);
+    }
+
+    #[tokio::test]
+    async fn search_reports_failed_requests() {
+        let server = MockServer::start(vec![(
+            "/search",
+            MockResponse::new(503, "text/html", "Down for maintenance"),
+        )])
+        .await;
+
+        let error = mangapill(&server).search("One Piece").await.unwrap_err();
+        assert_eq!(error.status().map(|s| s.as_u16()), Some(503));
+    }
+
+    #[tokio::test]
+    async fn test_get_info() {
+        let server = test_utils::mangapill_server().await;
+        let source = mangapill(&server);
+
+        let info = source.get_info(&comic(&server)).await.unwrap();
+
+        assert_eq!(
+            info,
+            Some(ComicInfo::new(
+                "1997",
+                "publishing",
+                vec![
+                    "Action".into(),
+                    "Adventure".into(),
+                    "Comedy".into(),
+                    "Fantasy".into()
+                ]
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_chapters() {
+        let server = test_utils::mangapill_server().await;
+        let source = mangapill(&server);
+
+        let chapters = source.get_chapters(&comic(&server)).await.unwrap();
+        let chapter =
+            |name: &str, path: &str| Chapter::new(name, &format!("{}{path}", server.url()));
+
+        assert_eq!(
+            chapters,
+            [
+                chapter(
+                    "Chapter 1101",
+                    "/chapters/2-11101000/one-piece-chapter-1101"
+                ),
+                chapter(
+                    "Chapter 1100",
+                    "/chapters/2-11100000/one-piece-chapter-1100"
+                ),
+                chapter("Chapter 1", "/chapters/2-10001000/one-piece-chapter-1"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_chapter() {
+        let server = test_utils::mangapill_server().await;
+        let source = mangapill(&server);
+        let chapter = Chapter::new(
+            "Chapter 1101",
+            &format!(
+                "{}/chapters/2-11101000/one-piece-chapter-1101",
+                server.url()
+            ),
+        );
+
+        let path = env::temp_dir().join("nika-mangapill-test");
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        source
+            .download_chapter(&chapter, &path, None)
+            .await
+            .unwrap();
+        let mut pages: Vec<_> = fs::read_dir(&path)
+            .unwrap()
+            .map(|f| fs::read(f.unwrap().path()).unwrap())
+            .collect();
+        pages.sort();
+
+        assert_eq!(pages, [vec![1; 64], vec![2; 128]]);
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn loads_user_definitions() {
+        let dir = env::temp_dir().join("nika-definitions-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // Only searches, with the results in a list and absolute links.
+        fs::write(
+            dir.join("pillclone.toml"),
+            r#"
+            id = "pillclone"
+            base_url = "https://pillclone.test/"
+
+            [search]
+            url = "{base_url}/find?term={query}"
+            results = "div.lg:grid-cols-5"
+            name = "div.leading-tight"
+            link = "a[href]"
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("broken.toml"),
+            "id = \"broken\"\n[search]\nurl = 1\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not a definition").unwrap();
+
+        let (definitions, errors) = load_definitions(&dir);
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("broken.toml"), "{}", errors[0]);
+
+        let server = MockServer::start(vec![(
+            "/find",
+            MockResponse::html(include_str!(
+                "../../../tests/fixtures/mangapill/search.html"
+            )),
+        )])
+        .await;
+        let definition = definitions[0].clone().with_base_url(&server.url());
+        assert_eq!(definitions[0].base_url, "https://pillclone.test");
+        let source = ConfigurableHtmlSource::with_client(definition, CLIENT.clone());
+        assert_eq!(source.name(), "pillclone");
+        assert_eq!(source.capabilities(), [Capability::Search]);
+
+        // Naruto is kept, since the query isn't matched.
+        let names: Vec<_> = source
+            .search("one piece & co")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, ["One Piece", "One Piece Party", "Naruto"]);
+        assert_eq!(server.requests(), ["/find?term=one+piece+%26+co"]);
+        assert_eq!(source.get_info(&comic(&server)).await.unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
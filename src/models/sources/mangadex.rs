@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::app::{CLIENT, NikaAction};
+use crate::helpers;
+use crate::models::comic::{Chapter, Comic, ComicInfo, ComicType};
+use crate::progress::{self, Phase, ProgressUpdate};
+use crate::traits::Source;
+
+const API_URL: &str = "https://api.mangadex.org";
+/// Maximum page size allowed by the chapter feed endpoint.
+const FEED_LIMIT: usize = 500;
+const SEARCH_LIMIT: usize = 25;
+
+/// Talks to MangaDex's JSON API instead of scraping its pages.
+pub struct MangaDexSource {
+    base_url: String,
+    client: Client,
+    languages: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Collection<T> {
+    data: Vec<T>,
+    total: usize,
+}
+
+#[derive(Deserialize)]
+struct Entity<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct Manga {
+    id: String,
+    attributes: MangaAttributes,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MangaAttributes {
+    title: HashMap<String, String>,
+    #[serde(default)]
+    alt_titles: Vec<HashMap<String, String>>,
+    year: Option<u32>,
+    status: Option<String>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    attributes: TagAttributes,
+}
+
+#[derive(Deserialize)]
+struct TagAttributes {
+    name: HashMap<String, String>,
+    group: String,
+}
+
+#[derive(Deserialize)]
+struct MdChapter {
+    id: String,
+    attributes: ChapterAttributes,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChapterAttributes {
+    chapter: Option<String>,
+    title: Option<String>,
+    external_url: Option<String>,
+    pages: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AtHome {
+    base_url: String,
+    chapter: AtHomeChapter,
+}
+
+#[derive(Deserialize)]
+struct AtHomeChapter {
+    hash: String,
+    data: Vec<String>,
+}
+
+#[async_trait]
+impl Source for MangaDexSource {
+    #[tracing::instrument(skip_all, fields(source = "mangadex", query = query))]
+    async fn search(&self, query: &str) -> reqwest::Result<Vec<Comic>> {
+        let mut params = vec![
+            ("title", query.to_owned()),
+            ("limit", SEARCH_LIMIT.to_string()),
+            ("order[relevance]", "desc".to_owned()),
+        ];
+        params.extend(self.language_params("availableTranslatedLanguage[]"));
+
+        let results: Collection<Manga> = helpers::send(
+            self.client
+                .get(format!("{}/manga", self.base_url))
+                .query(&params),
+        )
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+        let comics = results
+            .data
+            .into_iter()
+            .map(|m| {
+                let source = format!("{}/manga/{}", self.base_url, m.id);
+                Comic::new(
+                    &self.title(&m.attributes),
+                    &source,
+                    ComicType::Manga,
+                    Vec::new(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        tracing::info!(results = comics.len(), total = results.total, "searched");
+        Ok(comics)
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    #[tracing::instrument(skip_all, fields(source = "mangadex", url = %comic.source))]
+    async fn get_chapters(&self, comic: &Comic) -> reqwest::Result<Vec<Chapter>> {
+        let mut chapters = Vec::new();
+        let mut offset = 0;
+
+        // The feed is paginated, so keep asking until everything has been fetched.
+        loop {
+            let mut params = vec![
+                ("limit", FEED_LIMIT.to_string()),
+                ("offset", offset.to_string()),
+                ("order[chapter]", "desc".to_owned()),
+            ];
+            params.extend(self.language_params("translatedLanguage[]"));
+
+            let feed: Collection<MdChapter> = helpers::send(
+                self.client
+                    .get(format!("{}/feed", comic.source))
+                    .query(&params),
+            )
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+            let received = feed.data.len();
+            offset += received;
+            tracing::debug!(
+                received,
+                offset,
+                total = feed.total,
+                "read a page of the feed"
+            );
+
+            // External chapters (hosted on other sites) have no pages on the at-home servers.
+            chapters.extend(
+                feed.data
+                    .into_iter()
+                    .filter(|c| c.attributes.external_url.is_none() && c.attributes.pages > 0)
+                    .map(|c| {
+                        let source = format!("{}/chapter/{}", self.base_url, c.id);
+                        Chapter::new(&Self::chapter_name(&c.attributes), &source)
+                    }),
+            );
+
+            if received == 0 || offset >= feed.total {
+                break;
+            }
+        }
+
+        tracing::info!(chapters = chapters.len(), "listed chapters");
+        Ok(chapters)
+    }
+
+    #[tracing::instrument(skip_all, fields(source = "mangadex", url = %comic.source))]
+    async fn get_info(&self, comic: &Comic) -> reqwest::Result<Option<ComicInfo>> {
+        let manga: Entity<Manga> = helpers::send(self.client.get(&comic.source))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let attributes = manga.data.attributes;
+        let genres: Vec<String> = attributes
+            .tags
+            .iter()
+            .filter(|t| t.attributes.group == "genre")
+            .filter_map(|t| t.attributes.name.get("en").cloned())
+            .collect();
+
+        let date = attributes.year.map(|y| y.to_string()).unwrap_or_default();
+        let status = attributes.status.unwrap_or_default();
+        tracing::info!(genres = genres.len(), "read info");
+
+        Ok(Some(ComicInfo::new(&date, &status, genres)))
+    }
+
+    fn name(&self) -> &str {
+        "mangadex"
+    }
+
+    /// sender is used to update progress on loading screen.
+    #[tracing::instrument(skip_all, fields(source = "mangadex", url = %chapter.source))]
+    async fn download_chapter(
+        &self,
+        chapter: &Chapter,
+        dir: &Path,
+        sender: Option<UnboundedSender<NikaAction>>,
+    ) -> anyhow::Result<()> {
+        let id = chapter
+            .source
+            .rsplit('/')
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Invalid chapter url: {}", chapter.source))?;
+
+        progress::report(&sender, ProgressUpdate::Phase(Phase::FetchingChapter));
+        // Asks MangaDex which image server should be used for this chapter.
+        let at_home: AtHome = helpers::send(
+            self.client
+                .get(format!("{}/at-home/server/{id}", self.base_url)),
+        )
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+        progress::report(&sender, ProgressUpdate::Phase(Phase::ResolvingImages));
+
+        let urls: Vec<String> = at_home
+            .chapter
+            .data
+            .iter()
+            .map(|f| format!("{}/data/{}/{f}", at_home.base_url, at_home.chapter.hash))
+            .collect();
+        tracing::info!(images = urls.len(), "found pages");
+
+        helpers::download_images(&self.client, &urls, dir, &self.base_url, sender).await
+    }
+}
+
+impl MangaDexSource {
+    pub fn new() -> Self {
+        Self::with_base_url(API_URL, CLIENT.clone())
+    }
+
+    /// Points the source at another API server, e.g. a local stand-in.
+    pub fn with_base_url(base_url: &str, client: Client) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            client,
+            languages: vec![String::from("en")],
+        }
+    }
+
+    /// Only chapters translated into one of these languages (ISO 639-1 codes) are listed.
+    pub fn with_languages(mut self, languages: Vec<String>) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    fn language_params(&self, key: &'static str) -> Vec<(&'static str, String)> {
+        self.languages.iter().map(|l| (key, l.to_owned())).collect()
+    }
+
+    /// Prefers a title in one of the configured languages, then english, then whatever exists.
+    fn title(&self, attributes: &MangaAttributes) -> String {
+        let titles = std::iter::once(&attributes.title).chain(attributes.alt_titles.iter());
+
+        for lang in self.languages.iter().map(String::as_str).chain(["en"]) {
+            if let Some(title) = titles.clone().find_map(|t| t.get(lang)) {
+                return title.to_owned();
+            }
+        }
+
+        attributes
+            .title
+            .values()
+            .next()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn chapter_name(attributes: &ChapterAttributes) -> String {
+        let number = attributes.chapter.as_deref().unwrap_or("Oneshot");
+
+        match attributes.title.as_deref() {
+            Some(title) if !title.is_empty() => format!("Chapter {number}: {title}"),
+            _ => format!("Chapter {number}"),
+        }
+    }
+}
+
+impl Default for MangaDexSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::app::CLIENT;
+    use crate::models::comic::{Chapter, Comic, ComicType};
+    use crate::models::sources::mangadex::MangaDexSource;
+    use crate::test_utils::{MockResponse, MockServer};
+    use crate::traits::Source;
+
+    const MANGA_ID: &str = "a1c7c817-4e59-43b7-9365-09675a149a6f";
+    const CHAPTER_ID: &str = "6310f6a1-17ee-4890-b837-2ec1b372905b";
+    const HASH: &str = "1b9d4a6e4b7d3e1e9b5f3c6a1e2d4f8a";
+
+    async fn server() -> MockServer {
+        let manga = format!("/manga/{MANGA_ID}");
+
+        MockServer::start(vec![
+            (
+                "/manga",
+                MockResponse::json(include_str!("../../../tests/fixtures/mangadex/search.json")),
+            ),
+            (
+                &manga,
+                MockResponse::json(include_str!("../../../tests/fixtures/mangadex/manga.json")),
+            ),
+            (
+                &format!("{manga}/feed"),
+                MockResponse::json(include_str!("../../../tests/fixtures/mangadex/feed.json")),
+            ),
+            (
+                &format!("/at-home/server/{CHAPTER_ID}"),
+                MockResponse::json(include_str!(
+                    "../../../tests/fixtures/mangadex/at_home.json"
+                )),
+            ),
+            (
+                &format!("/data/{HASH}/1-page.png"),
+                MockResponse::new(200, "image/png", vec![1; 16]),
+            ),
+            (
+                &format!("/data/{HASH}/2-page.png"),
+                MockResponse::new(200, "image/png", vec![2; 32]),
+            ),
+        ])
+        .await
+    }
+
+    fn comic(server: &MockServer) -> Comic {
+        let source = format!("{}/manga/{MANGA_ID}", server.url());
+        Comic::new("One Piece", &source, ComicType::Manga, Vec::new())
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = server().await;
+        let source = MangaDexSource::with_base_url(&server.url(), CLIENT.clone());
+
+        let results = source.search("one piece").await.unwrap();
+        let names: Vec<_> = results.iter().map(|c| c.name.as_str()).collect();
+
+        assert_eq!(names, ["One Piece", "One Piece: Ace's Story"]);
+        assert_eq!(
+            results[0].source,
+            format!("{}/manga/{MANGA_ID}", server.url())
+        );
+
+        let request = &server.requests()[0];
+        assert!(request.contains("title=one+piece"));
+        assert!(request.contains("availableTranslatedLanguage%5B%5D=en"));
+    }
+
+    #[tokio::test]
+    async fn test_get_chapters_filters_external_chapters() {
+        let server = server().await;
+        let source = MangaDexSource::with_base_url(&server.url(), CLIENT.clone())
+            .with_languages(vec!["en".into(), "pt-br".into()]);
+
+        let chapters = source.get_chapters(&comic(&server)).await.unwrap();
+        let chapter =
+            |id: &str, name: &str| Chapter::new(name, &format!("{}/chapter/{id}", server.url()));
+
+        assert_eq!(
+            chapters,
+            [
+                chapter(CHAPTER_ID, "Chapter 1101: The Sun God"),
+                chapter("2b3c9f7e-0d6e-4c1a-a3a4-9d9c1f8e7b21", "Chapter 1100"),
+            ]
+        );
+
+        let request = &server.requests()[0];
+        assert!(request.contains("translatedLanguage%5B%5D=en"));
+        assert!(request.contains("translatedLanguage%5B%5D=pt-br"));
+    }
+
+    #[tokio::test]
+    async fn test_get_info() {
+        let server = server().await;
+        let source = MangaDexSource::with_base_url(&server.url(), CLIENT.clone());
+
+        let info = source.get_info(&comic(&server)).await.unwrap().unwrap();
+
+        assert_eq!(info.date, "1997");
+        assert_eq!(info.status, "ongoing");
+        assert_eq!(info.genres, ["Action", "Adventure", "Comedy"]);
+    }
+
+    #[tokio::test]
+    async fn test_download_chapter() {
+        let server = server().await;
+        let source = MangaDexSource::with_base_url(&server.url(), CLIENT.clone());
+        let chapter = Chapter::new(
+            "Chapter 1101",
+            &format!("{}/chapter/{CHAPTER_ID}", server.url()),
+        );
+
+        let path = env::temp_dir().join("nika-mangadex-test");
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        source
+            .download_chapter(&chapter, &path, None)
+            .await
+            .unwrap();
+        let mut sizes: Vec<_> = fs::read_dir(&path)
+            .unwrap()
+            .map(|f| f.unwrap().metadata().unwrap().len())
+            .collect();
+        sizes.sort();
+
+        assert_eq!(sizes, [16, 32]);
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -1 +1,3 @@
-pub mod mangapill;
+pub mod html;
+pub mod mangadex;
+pub mod registry;
@@ -0,0 +1,222 @@
+use std::path::Path;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::sources::html::{self, ConfigurableHtmlSource, SourceDefinition};
+use crate::models::sources::mangadex::MangaDexSource;
+use crate::traits::Source;
+
+/// Something a source can do. Pages only offer a source for what it can do.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Search,
+    /// Lists a comic's chapters.
+    Chapters,
+    /// Reads a comic's status, date and genres.
+    Info,
+    Download,
+}
+
+impl Capability {
+    pub const ALL: &'static [Capability] = &[
+        Capability::Search,
+        Capability::Chapters,
+        Capability::Info,
+        Capability::Download,
+    ];
+}
+
+/// The `[sources]` table of config.toml.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SourcesConfig {
+    /// Ids of the sources to leave out, e.g. `["mangadex"]`.
+    pub disabled: Vec<String>,
+}
+
+/// A source, as listed by [`SourceRegistry::list`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SourceSummary {
+    pub id: String,
+    pub base_url: String,
+    pub enabled: bool,
+    pub capabilities: Vec<Capability>,
+}
+
+struct Entry {
+    source: Arc<dyn Source>,
+    enabled: bool,
+}
+
+/// Every source nika-tui knows, found by id. Which ones are enabled comes from the config.
+/// Cloning gives another handle to the same sources.
+#[derive(Clone)]
+pub struct SourceRegistry {
+    entries: Arc<RwLock<Vec<Entry>>>,
+}
+
+impl SourceRegistry {
+    /// The built-in sources, and those defined in `definitions_dir`. A definition replaces the
+    /// built-in source with the same id. Also returns what was wrong with the definitions.
+    pub fn new(config: &SourcesConfig, definitions_dir: &Path) -> (Self, Vec<String>) {
+        let mut sources: Vec<Arc<dyn Source>> = vec![
+            Arc::new(ConfigurableHtmlSource::new(SourceDefinition::mangapill())),
+            Arc::new(MangaDexSource::new()),
+        ];
+
+        let (definitions, errors) = html::load_definitions(definitions_dir);
+        for definition in definitions {
+            tracing::info!(id = definition.id, "loaded a source definition");
+            let source = Arc::new(ConfigurableHtmlSource::new(definition));
+            match sources.iter().position(|s| s.name() == source.name()) {
+                Some(index) => sources[index] = source,
+                None => sources.push(source),
+            }
+        }
+
+        (Self::with_sources(sources, config), errors)
+    }
+
+    pub fn with_sources(sources: Vec<Arc<dyn Source>>, config: &SourcesConfig) -> Self {
+        let entries = sources
+            .into_iter()
+            .map(|source| Entry {
+                source,
+                enabled: true,
+            })
+            .collect();
+
+        let registry = Self {
+            entries: Arc::new(RwLock::new(entries)),
+        };
+        registry.reconfigure(config);
+        registry
+    }
+
+    /// Enables and disables sources as the config says.
+    pub fn reconfigure(&self, config: &SourcesConfig) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+
+        for entry in entries.iter_mut() {
+            entry.enabled = !config.disabled.iter().any(|id| id == entry.source.name());
+        }
+        for id in &config.disabled {
+            if !entries.iter().any(|e| e.source.name() == id) {
+                tracing::warn!(id, "can't disable an unknown source");
+            }
+        }
+    }
+
+    /// The source with the given id, unless it's disabled.
+    pub fn get(&self, id: &str) -> anyhow::Result<Arc<dyn Source>> {
+        let entries = self.read();
+        let entry = entries
+            .iter()
+            .find(|e| e.source.name() == id)
+            .ok_or_else(|| anyhow::anyhow!("{id} isn't a known source"))?;
+
+        anyhow::ensure!(entry.enabled, "{id} is disabled in the config");
+        Ok(entry.source.clone())
+    }
+
+    /// The enabled sources that can do `capability`, in the order they were added.
+    pub fn enabled(&self, capability: Capability) -> Vec<Arc<dyn Source>> {
+        self.read()
+            .iter()
+            .filter(|e| e.enabled && e.source.capabilities().contains(&capability))
+            .map(|e| e.source.clone())
+            .collect()
+    }
+
+    /// The enabled source whose site `url` is on.
+    pub fn for_url(&self, url: &str) -> Option<Arc<dyn Source>> {
+        self.read()
+            .iter()
+            .find(|e| e.enabled && url.starts_with(e.source.base_url()))
+            .map(|e| e.source.clone())
+    }
+
+    /// Every source, enabled or not.
+    pub fn list(&self) -> Vec<SourceSummary> {
+        self.read()
+            .iter()
+            .map(|e| SourceSummary {
+                id: e.source.name().to_owned(),
+                base_url: e.source.base_url().to_owned(),
+                enabled: e.enabled,
+                capabilities: e.source.capabilities().to_vec(),
+            })
+            .collect()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Vec<Entry>> {
+        // Nothing can be left half changed by a panic.
+        self.entries.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Capability, SourceRegistry, SourcesConfig};
+    use crate::app::CLIENT;
+    use crate::models::sources::html::{ConfigurableHtmlSource, SourceDefinition};
+    use crate::models::sources::mangadex::MangaDexSource;
+
+    fn registry(disabled: &[&str]) -> SourceRegistry {
+        let config = SourcesConfig {
+            disabled: disabled.iter().map(|id| id.to_string()).collect(),
+        };
+        SourceRegistry::with_sources(
+            vec![
+                Arc::new(ConfigurableHtmlSource::with_client(
+                    SourceDefinition::mangapill().with_base_url("https://mangapill.test"),
+                    CLIENT.clone(),
+                )),
+                Arc::new(MangaDexSource::with_base_url(
+                    "https://api.mangadex.test",
+                    CLIENT.clone(),
+                )),
+            ],
+            &config,
+        )
+    }
+
+    fn ids(registry: &SourceRegistry) -> Vec<String> {
+        registry
+            .enabled(Capability::Search)
+            .iter()
+            .map(|s| s.name().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn finds_sources_by_id_and_url() {
+        let registry = registry(&[]);
+
+        assert_eq!(registry.get("mangadex").unwrap().name(), "mangadex");
+        assert!(registry.get("mangasee").is_err());
+
+        let url = "https://mangapill.test/manga/2/one-piece";
+        assert_eq!(registry.for_url(url).unwrap().name(), "mangapill");
+        assert!(registry.for_url("https://example.com/manga/2").is_none());
+    }
+
+    #[test]
+    fn leaves_out_disabled_sources() {
+        let registry = registry(&["mangadex"]);
+
+        assert_eq!(ids(&registry), ["mangapill"]);
+        assert!(registry.get("mangadex").is_err());
+        let listed: Vec<_> = registry.list().into_iter().map(|s| s.enabled).collect();
+        assert_eq!(listed, [true, false]);
+
+        // Every handle sees the change.
+        let handle = registry.clone();
+        registry.reconfigure(&SourcesConfig::default());
+        assert_eq!(ids(&handle), ["mangapill", "mangadex"]);
+    }
+}
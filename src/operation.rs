@@ -0,0 +1,88 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::future::{AbortHandle, Abortable};
+
+/// A task running behind the loading screen, which Esc cancels.
+#[derive(Clone)]
+pub struct Operation {
+    /// Tells it apart from other operations, e.g. to find its loading screen.
+    id: u64,
+    handle: AbortHandle,
+}
+
+impl PartialEq for Operation {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Operation {
+    /// Runs the future built by `task` in the background. It gets the operation, to hand it to the
+    /// loading screens it shows.
+    pub fn spawn<F>(task: impl FnOnce(Operation) -> F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let (handle, registration) = AbortHandle::new_pair();
+        let operation = Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            handle,
+        };
+
+        // Aborting drops the future, so whatever it holds gets cleaned up.
+        tokio::spawn(Abortable::new(task(operation), registration));
+    }
+
+    pub fn cancel(&self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use tokio::sync::oneshot;
+
+    use super::Operation;
+
+    #[tokio::test]
+    async fn cancelling_stops_the_task() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = oneshot::channel();
+
+        let flag = finished.clone();
+        Operation::spawn(|operation| async move {
+            tx.send(operation).ok().unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        let operation = rx.await.unwrap();
+        operation.cancel();
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(!finished.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn operations_are_told_apart() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for _ in 0..2 {
+            let tx = tx.clone();
+            Operation::spawn(|operation| async move {
+                tx.send(operation).unwrap();
+            });
+        }
+
+        let first = rx.recv().await.unwrap();
+        let second = rx.recv().await.unwrap();
+        assert!(first == first.clone());
+        assert!(first != second);
+    }
+}
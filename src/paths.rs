@@ -0,0 +1,151 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::{env, fs, io};
+
+use crate::constants::{APP_DIR, CONFIG_FILE, SOURCES_DIR};
+
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+/// Where nika-tui keeps its files, following the XDG base directory spec.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paths {
+    pub config_file: PathBuf,
+    /// Library, reading history and downloads.
+    pub data_dir: PathBuf,
+    /// Logs, which are worth keeping between runs but not backing up.
+    pub state_dir: PathBuf,
+}
+
+impl Paths {
+    /// Resolves the directories from `env`, unless given. `XDG_*_HOME` wins over `HOME`, and the
+    /// temp dir is the last resort.
+    pub fn resolve(
+        config_file: Option<PathBuf>,
+        data_dir: Option<PathBuf>,
+        env: impl Fn(&str) -> Option<OsString>,
+    ) -> Self {
+        let base = |var: &str, fallback: &str| {
+            let xdg = env(var).map(PathBuf::from).filter(|p| p.is_absolute());
+            let home = || env("HOME").map(|home| Path::new(&home).join(fallback));
+
+            xdg.or_else(home)
+                .unwrap_or_else(env::temp_dir)
+                .join(APP_DIR)
+        };
+
+        Self {
+            config_file: config_file
+                .unwrap_or_else(|| base("XDG_CONFIG_HOME", ".config").join(CONFIG_FILE)),
+            data_dir: data_dir.unwrap_or_else(|| base("XDG_DATA_HOME", ".local/share")),
+            state_dir: base("XDG_STATE_HOME", ".local/state"),
+        }
+    }
+
+    /// Source definitions, next to the config file.
+    pub fn sources_dir(&self) -> PathBuf {
+        let config_dir = self.config_file.parent().unwrap_or(Path::new(""));
+        config_dir.join(SOURCES_DIR)
+    }
+
+    pub fn data_file(&self, name: &str) -> PathBuf {
+        self.data_dir.join(name)
+    }
+
+    /// Moves files from where older versions kept them into the data dir, unless they're there
+    /// already.
+    pub fn migrate(&self, old_dir: &Path, names: &[&str]) -> io::Result<()> {
+        for name in names {
+            let (old, new) = (old_dir.join(name), self.data_file(name));
+            if old.exists() && !new.exists() {
+                fs::create_dir_all(&self.data_dir)?;
+                fs::rename(old, new)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Sets the paths for the rest of the run. Only the first call counts.
+pub fn init(paths: Paths) {
+    let _ = PATHS.set(paths);
+}
+
+/// The paths given to [`init`], or the defaults if it wasn't called.
+pub fn get() -> &'static Paths {
+    PATHS.get_or_init(|| Paths::resolve(None, None, |var| env::var_os(var)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use std::path::{Path, PathBuf};
+    use std::{env, fs};
+
+    use super::Paths;
+
+    fn resolve(vars: &[(&str, &str)]) -> Paths {
+        let vars: HashMap<&str, OsString> = vars.iter().map(|(k, v)| (*k, v.into())).collect();
+        Paths::resolve(None, None, |var| vars.get(var).cloned())
+    }
+
+    #[test]
+    fn prefers_xdg_over_home() {
+        let paths = resolve(&[
+            ("HOME", "/home/nika"),
+            ("XDG_CONFIG_HOME", "/xdg/config"),
+            ("XDG_DATA_HOME", "relative/is/ignored"),
+        ]);
+
+        assert_eq!(
+            paths.config_file,
+            Path::new("/xdg/config/nika-tui/config.toml")
+        );
+        assert_eq!(
+            paths.data_dir,
+            Path::new("/home/nika/.local/share/nika-tui")
+        );
+        assert_eq!(
+            paths.state_dir,
+            Path::new("/home/nika/.local/state/nika-tui")
+        );
+    }
+
+    #[test]
+    fn flags_override_everything() {
+        let paths = Paths::resolve(
+            Some(PathBuf::from("work.toml")),
+            Some(PathBuf::from("/srv/nika")),
+            |_| None,
+        );
+
+        assert_eq!(paths.config_file, Path::new("work.toml"));
+        assert_eq!(
+            paths.data_file("library.toml"),
+            Path::new("/srv/nika/library.toml")
+        );
+        assert!(paths.state_dir.starts_with(env::temp_dir()));
+    }
+
+    #[test]
+    fn moves_old_files_into_the_data_dir() {
+        let root = env::temp_dir().join("nika-paths-test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("config")).unwrap();
+        fs::write(root.join("config/library.toml"), "old").unwrap();
+
+        let paths = Paths::resolve(None, Some(root.join("data")), |_| None);
+        paths
+            .migrate(&root.join("config"), &["library.toml", "history.toml"])
+            .unwrap();
+
+        assert!(!root.join("config/library.toml").exists());
+        assert_eq!(
+            fs::read_to_string(root.join("data/library.toml")).unwrap(),
+            "old"
+        );
+        assert!(!root.join("data/history.toml").exists());
+    }
+}
@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::app::NikaAction;
+
+/// What a download is busy with.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Phase {
+    /// Requesting the chapter's page, or whatever lists its images.
+    #[default]
+    FetchingChapter,
+    ResolvingImages,
+    Downloading,
+}
+
+impl Phase {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Phase::FetchingChapter => "Fetching chapter",
+            Phase::ResolvingImages => "Resolving images",
+            Phase::Downloading => "Downloading pages",
+        }
+    }
+}
+
+/// Sent by sources while they download a chapter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressUpdate {
+    Phase(Phase),
+    /// How many pages the chapter has. Also starts the download phase.
+    PageCount(usize),
+    /// A page's response came in, with its size if the server said.
+    PageSize(Option<u64>),
+    Bytes(u64),
+    /// Bytes of a failed attempt, that no longer count.
+    Discarded(u64),
+    PageDone,
+}
+
+/// Sends an update if anybody is listening.
+pub fn report(sender: &Option<UnboundedSender<NikaAction>>, update: ProgressUpdate) {
+    if let Some(sender) = sender {
+        // The receiver might be gone already, which is fine.
+        let _ = sender.send(NikaAction::Progress(update));
+    }
+}
+
+/// Everything known about a running download, built from [`ProgressUpdate`]s.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    pub phase: Phase,
+    pub pages_done: usize,
+    pub page_count: usize,
+    pub bytes: u64,
+    /// Pages whose size is known, and their sum.
+    sized_pages: usize,
+    size_sum: u64,
+}
+
+impl Progress {
+    pub fn apply(&mut self, update: ProgressUpdate) {
+        match update {
+            ProgressUpdate::Phase(phase) => self.phase = phase,
+            ProgressUpdate::PageCount(count) => {
+                self.phase = Phase::Downloading;
+                self.page_count = count;
+            }
+            ProgressUpdate::PageSize(Some(size)) => {
+                self.sized_pages += 1;
+                self.size_sum += size;
+            }
+            ProgressUpdate::PageSize(None) => {}
+            ProgressUpdate::Bytes(bytes) => self.bytes += bytes,
+            ProgressUpdate::Discarded(bytes) => self.bytes = self.bytes.saturating_sub(bytes),
+            ProgressUpdate::PageDone => self.pages_done += 1,
+        }
+    }
+
+    /// Only known once every page's size is, since compressed or chunked responses don't tell.
+    pub fn total_bytes(&self) -> Option<u64> {
+        (self.page_count > 0 && self.sized_pages == self.page_count).then_some(self.size_sum)
+    }
+
+    /// Between 0 and 1, by bytes if possible and by pages otherwise.
+    pub fn fraction(&self) -> f64 {
+        let fraction = match self.total_bytes() {
+            Some(total) if total > 0 => self.bytes as f64 / total as f64,
+            _ if self.page_count > 0 => self.pages_done as f64 / self.page_count as f64,
+            _ => 0.0,
+        };
+
+        fraction.clamp(0.0, 1.0)
+    }
+
+    /// Bytes per second, given how long pages have been downloading.
+    pub fn speed(&self, elapsed: Duration) -> Option<f64> {
+        let seconds = elapsed.as_secs_f64();
+        (seconds > 0.0 && self.bytes > 0).then(|| self.bytes as f64 / seconds)
+    }
+
+    /// Time left at the current pace.
+    pub fn eta(&self, elapsed: Duration) -> Option<Duration> {
+        let fraction = self.fraction();
+        if fraction <= 0.0 {
+            return None;
+        }
+
+        let left = elapsed.as_secs_f64() * (1.0 - fraction) / fraction;
+        Some(Duration::from_secs_f64(left))
+    }
+}
+
+/// Sizes like "1.5 MiB".
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{size:.0} {}", UNITS[unit]),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
+}
+
+/// Durations like "1m 05s".
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64().ceil() as u64;
+
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        _ => format!("{}m {:02}s", seconds / 60, seconds % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Phase, Progress, ProgressUpdate, format_bytes, format_duration};
+
+    fn progress(updates: Vec<ProgressUpdate>) -> Progress {
+        let mut progress = Progress::default();
+        updates.into_iter().for_each(|u| progress.apply(u));
+        progress
+    }
+
+    #[test]
+    fn counts_bytes_once_every_size_is_known() {
+        let mut p = progress(vec![
+            ProgressUpdate::PageCount(2),
+            ProgressUpdate::PageSize(Some(100)),
+            ProgressUpdate::Bytes(50),
+        ]);
+        assert_eq!(p.phase, Phase::Downloading);
+        assert_eq!(p.total_bytes(), None);
+        assert_eq!(p.fraction(), 0.0);
+
+        p.apply(ProgressUpdate::PageSize(Some(300)));
+        assert_eq!(p.total_bytes(), Some(400));
+        assert_eq!(p.fraction(), 0.125);
+
+        let eta = p.eta(Duration::from_secs(1)).unwrap();
+        assert_eq!(eta, Duration::from_secs(7));
+        assert_eq!(p.speed(Duration::from_secs(2)), Some(25.0));
+    }
+
+    #[test]
+    fn falls_back_to_pages_without_sizes() {
+        let p = progress(vec![
+            ProgressUpdate::PageCount(4),
+            ProgressUpdate::PageSize(None),
+            ProgressUpdate::Bytes(5000),
+            ProgressUpdate::PageDone,
+        ]);
+
+        assert_eq!(p.total_bytes(), None);
+        assert_eq!(p.fraction(), 0.25);
+    }
+
+    #[test]
+    fn never_goes_past_the_end() {
+        // A response that was bigger than it said.
+        let p = progress(vec![
+            ProgressUpdate::PageCount(1),
+            ProgressUpdate::PageSize(Some(10)),
+            ProgressUpdate::Bytes(20),
+        ]);
+
+        assert_eq!(p.fraction(), 1.0);
+    }
+
+    #[test]
+    fn formats_sizes_and_durations() {
+        assert_eq!(format_bytes(512.0), "512 B");
+        assert_eq!(format_bytes(1536.0), "1.5 KiB");
+        assert_eq!(format_bytes(3.0 * 1024.0 * 1024.0), "3.0 MiB");
+        assert_eq!(format_duration(Duration::from_millis(4200)), "5s");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1m 05s");
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::{env, fs};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Writes `contents` to `name.toml` in `dir` under the temp dir, e.g. a config file for a test to
+/// load. Tests share a dir, so each one uses its own name.
+pub fn temp_file(dir: &str, name: &str, contents: &str) -> PathBuf {
+    let dir = env::temp_dir().join(dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(format!("{name}.toml"));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Placeholder replaced with the server's address in every text body, so fixtures can link back
+/// to the stand-in (e.g. image urls).
+pub const BASE_URL_PLACEHOLDER: &str = "{{base_url}}";
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            content_type: content_type.to_owned(),
+            body: body.into(),
+        }
+    }
+
+    pub fn json(body: &str) -> Self {
+        Self::new(200, "application/json", body)
+    }
+
+    pub fn html(body: &str) -> Self {
+        Self::new(200, "text/html; charset=utf-8", body)
+    }
+}
+
+/// Responses of a path, served in order. The last one is repeated once the others are used up.
+type Sequence = Mutex<VecDeque<MockResponse>>;
+
+/// Minimal local HTTP stand-in used by source tests. Requests are matched by path (query strings
+/// are ignored); unknown paths get a 404.
+pub struct MockServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    pub async fn start(routes: Vec<(&str, MockResponse)>) -> Self {
+        let routes = routes
+            .into_iter()
+            .map(|(path, r)| (path, vec![r]))
+            .collect();
+        Self::start_with_sequences(routes).await
+    }
+
+    /// Like [`MockServer::start`], but each path answers with the given responses in turn, e.g. to
+    /// fail a couple of times before succeeding.
+    pub async fn start_with_sequences(routes: Vec<(&str, Vec<MockResponse>)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let base_url = format!("http://{addr}");
+
+        let routes: HashMap<String, Sequence> = routes
+            .into_iter()
+            .map(|(path, responses)| {
+                let responses = responses
+                    .into_iter()
+                    .map(|mut response| {
+                        if let Ok(text) = String::from_utf8(response.body.clone()) {
+                            response.body =
+                                text.replace(BASE_URL_PLACEHOLDER, &base_url).into_bytes();
+                        }
+                        response
+                    })
+                    .collect();
+                (path.to_owned(), Mutex::new(responses))
+            })
+            .collect();
+
+        let routes = Arc::new(routes);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let routes = routes.clone();
+                let log = log.clone();
+                tokio::spawn(async move {
+                    let _ = Self::handle(stream, &routes, &log).await;
+                });
+            }
+        });
+
+        Self { addr, requests }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Every request target (path and query) received so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    async fn handle(
+        mut stream: TcpStream,
+        routes: &HashMap<String, Sequence>,
+        log: &Mutex<Vec<String>>,
+    ) -> std::io::Result<()> {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+
+        let head = String::from_utf8_lossy(&buf);
+        let target = head.split_whitespace().nth(1).unwrap_or("/").to_owned();
+        let path = target.split('?').next().unwrap_or("/").to_owned();
+        log.lock().unwrap().push(target);
+
+        let response = routes
+            .get(&path)
+            .and_then(|sequence| {
+                let mut sequence = sequence.lock().unwrap();
+                match sequence.len() {
+                    0 | 1 => sequence.front().cloned(),
+                    _ => sequence.pop_front(),
+                }
+            })
+            .unwrap_or_else(|| MockResponse::new(404, "text/plain", "not found"));
+
+        let header = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: \
+             close\r\n\r\n",
+            response.status,
+            response.content_type,
+            response.body.len()
+        );
+
+        stream.write_all(header.as_bytes()).await?;
+        stream.write_all(&response.body).await?;
+        stream.shutdown().await
+    }
+}
+
+/// A stand-in for Mangapill, with the search, One Piece's page, its chapter 1101 and that
+/// chapter's two pages. Shared by the source and command line tests.
+pub async fn mangapill_server() -> MockServer {
+    MockServer::start(vec![
+        (
+            "/search",
+            MockResponse::html(include_str!("../tests/fixtures/mangapill/search.html")),
+        ),
+        (
+            "/manga/2/one-piece",
+            MockResponse::html(include_str!("../tests/fixtures/mangapill/manga.html")),
+        ),
+        (
+            "/chapters/2-11101000/one-piece-chapter-1101",
+            MockResponse::html(include_str!("../tests/fixtures/mangapill/chapter.html")),
+        ),
+        (
+            "/images/1101-1.jpeg",
+            MockResponse::new(200, "image/jpeg", vec![1; 64]),
+        ),
+        (
+            "/images/1101-2.jpeg",
+            MockResponse::new(200, "image/jpeg", vec![2; 128]),
+        ),
+    ])
+    .await
+}
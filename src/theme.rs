@@ -0,0 +1,232 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+/// The built-in themes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    Gruvbox,
+    /// No colors at all, for terminals without them. Used whenever `NO_COLOR` is set.
+    Monochrome,
+}
+
+/// The `[theme]` table of config.toml.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub name: ThemeName,
+    /// A TOML file with colors that replace the named theme's, e.g. `border = "#83a598"`.
+    pub file: Option<PathBuf>,
+}
+
+/// The colors every page draws with. `reset` means none, and the styles fall back to modifiers
+/// like bold or reversed so things still stand out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub border: Color,
+    pub title: Color,
+    /// The selected item of a list.
+    pub highlight: Color,
+    /// Things that matter less, like read chapters.
+    pub dimmed: Color,
+    pub error: Color,
+    /// Whatever is active or done, like the focused box or a finished download.
+    pub accent: Color,
+    /// Things that need attention but aren't errors, like paused downloads.
+    pub warning: Color,
+}
+
+/// A theme file. Every color is optional.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    border: Option<Color>,
+    title: Option<Color>,
+    highlight: Option<Color>,
+    dimmed: Option<Color>,
+    error: Option<Color>,
+    accent: Option<Color>,
+    warning: Option<Color>,
+}
+
+impl Theme {
+    pub const DEFAULT: Self = Self {
+        border: Color::Cyan,
+        title: Color::LightRed,
+        highlight: Color::Yellow,
+        dimmed: Color::DarkGray,
+        error: Color::Red,
+        accent: Color::Green,
+        warning: Color::Yellow,
+    };
+
+    pub const GRUVBOX: Self = Self {
+        border: Color::Rgb(0x83, 0xa5, 0x98),
+        title: Color::Rgb(0xfe, 0x80, 0x19),
+        highlight: Color::Rgb(0xfa, 0xbd, 0x2f),
+        dimmed: Color::Rgb(0x92, 0x83, 0x74),
+        error: Color::Rgb(0xfb, 0x49, 0x34),
+        accent: Color::Rgb(0xb8, 0xbb, 0x26),
+        warning: Color::Rgb(0xd7, 0x99, 0x21),
+    };
+
+    pub const MONOCHROME: Self = Self {
+        border: Color::Reset,
+        title: Color::Reset,
+        highlight: Color::Reset,
+        dimmed: Color::Reset,
+        error: Color::Reset,
+        accent: Color::Reset,
+        warning: Color::Reset,
+    };
+
+    /// The theme `config` asks for, unless `no_color` is set. Also returns why the theme file
+    /// couldn't be used, if it couldn't.
+    pub fn new(config: &ThemeConfig, no_color: bool) -> (Self, Option<String>) {
+        if no_color {
+            return (Self::MONOCHROME, None);
+        }
+
+        let theme = match config.name {
+            ThemeName::Default => Self::DEFAULT,
+            ThemeName::Gruvbox => Self::GRUVBOX,
+            ThemeName::Monochrome => Self::MONOCHROME,
+        };
+        let Some(path) = &config.file else {
+            return (theme, None);
+        };
+
+        let file = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(toml::from_str::<ThemeFile>(&data)?));
+        match file {
+            Ok(file) => (theme.with(file), None),
+            Err(e) => {
+                let message = e.to_string();
+                let message = message.lines().next().unwrap_or_default();
+                (theme, Some(format!("theme.file: {message}")))
+            }
+        }
+    }
+
+    fn with(self, file: ThemeFile) -> Self {
+        Self {
+            border: file.border.unwrap_or(self.border),
+            title: file.title.unwrap_or(self.title),
+            highlight: file.highlight.unwrap_or(self.highlight),
+            dimmed: file.dimmed.unwrap_or(self.dimmed),
+            error: file.error.unwrap_or(self.error),
+            accent: file.accent.unwrap_or(self.accent),
+            warning: file.warning.unwrap_or(self.warning),
+        }
+    }
+
+    pub fn border(&self) -> Style {
+        style(self.border, Modifier::empty())
+    }
+
+    pub fn title(&self) -> Style {
+        style(self.title, Modifier::empty()).add_modifier(Modifier::BOLD)
+    }
+
+    pub fn highlight(&self) -> Style {
+        style(self.highlight, Modifier::REVERSED)
+    }
+
+    pub fn dimmed(&self) -> Style {
+        style(self.dimmed, Modifier::DIM)
+    }
+
+    pub fn error(&self) -> Style {
+        style(self.error, Modifier::BOLD)
+    }
+
+    pub fn accent(&self) -> Style {
+        style(self.accent, Modifier::BOLD)
+    }
+
+    pub fn warning(&self) -> Style {
+        style(self.warning, Modifier::ITALIC)
+    }
+}
+
+/// The color, or `fallback` without one.
+fn style(color: Color, fallback: Modifier) -> Style {
+    match color {
+        Color::Reset => Style::new().add_modifier(fallback),
+        color => Style::new().fg(color),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Color, Modifier, Style};
+
+    use super::{Theme, ThemeConfig, ThemeName};
+    use crate::test_utils;
+
+    #[test]
+    fn files_replace_some_colors() {
+        let config = ThemeConfig {
+            name: ThemeName::Gruvbox,
+            file: Some(test_utils::temp_file(
+                "nika-theme-test",
+                "partial",
+                "border = \"blue\"\nerror = \"#ff0000\"",
+            )),
+        };
+
+        let (theme, error) = Theme::new(&config, false);
+
+        assert_eq!(error, None);
+        assert_eq!(theme.border, Color::Blue);
+        assert_eq!(theme.error, Color::Rgb(0xff, 0, 0));
+        assert_eq!(theme.accent, Theme::GRUVBOX.accent);
+    }
+
+    #[test]
+    fn bad_files_are_reported() {
+        for (name, contents) in [
+            ("unknown", "boder = \"blue\""),
+            ("color", "border = \"blu\""),
+        ] {
+            let config = ThemeConfig {
+                name: ThemeName::Default,
+                file: Some(test_utils::temp_file("nika-theme-test", name, contents)),
+            };
+
+            let (theme, error) = Theme::new(&config, false);
+
+            assert_eq!(theme, Theme::DEFAULT);
+            assert!(error.unwrap().starts_with("theme.file: "));
+        }
+    }
+
+    #[test]
+    fn no_color_wins() {
+        let config = ThemeConfig {
+            name: ThemeName::Gruvbox,
+            file: Some(test_utils::temp_file(
+                "nika-theme-test",
+                "ignored",
+                "border = \"blue\"",
+            )),
+        };
+
+        let (theme, error) = Theme::new(&config, true);
+
+        assert_eq!(theme, Theme::MONOCHROME);
+        assert_eq!(error, None);
+        // Still readable without colors.
+        assert_eq!(
+            theme.highlight(),
+            Style::new().add_modifier(Modifier::REVERSED)
+        );
+        assert_eq!(theme.dimmed(), Style::new().add_modifier(Modifier::DIM));
+    }
+}
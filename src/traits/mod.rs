@@ -1,4 +1,5 @@
 use std::io;
+use std::path::Path;
 
 use async_trait::async_trait;
 use crossterm::event::KeyEvent;
@@ -7,7 +8,11 @@ use ratatui::Frame;
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::app::NikaAction;
+use crate::keymap::{Action, Context, Keymap};
 use crate::models::comic::{Chapter, Comic, ComicInfo};
+use crate::models::sources::registry::Capability;
+use crate::operation::Operation;
+use crate::theme::Theme;
 use crate::tui::NikaEvent;
 
 #[async_trait]
@@ -15,40 +20,79 @@ pub trait Source: Send + Sync {
     /// Returns a list of search results based on query
     async fn search(&self, query: &str) -> reqwest::Result<Vec<Comic>>;
 
-    fn base_url(&self) -> &'static str;
+    fn base_url(&self) -> &str;
 
     /// Returns the chapters for a given comic
     async fn get_chapters(&self, comic: &Comic) -> reqwest::Result<Vec<Chapter>>;
 
     async fn get_info(&self, comic: &Comic) -> reqwest::Result<Option<ComicInfo>>;
 
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
 
+    /// What the source can do. Everything, unless it says otherwise.
+    fn capabilities(&self) -> &[Capability] {
+        Capability::ALL
+    }
+
+    /// Saves the pages of a chapter into `dir`, which already exists.
     async fn download_chapter(
         &self,
         chapter: &Chapter,
+        dir: &Path,
         sender: Option<UnboundedSender<NikaAction>>,
-    ) -> anyhow::Result<String>;
+    ) -> anyhow::Result<()>;
 }
 
 pub trait Component {
     #[allow(unused_variables)]
     fn init(&mut self, tx: UnboundedSender<NikaAction>) -> io::Result<()>;
 
-    fn handle_events(&mut self, event: Option<NikaEvent>) -> anyhow::Result<Option<NikaAction>> {
+    /// Where the page is, for which bindings apply.
+    fn context(&self) -> Context {
+        Context::Global
+    }
+
+    fn handle_events(
+        &mut self,
+        event: Option<NikaEvent>,
+        keymap: &Keymap,
+    ) -> anyhow::Result<Option<NikaAction>> {
         let r = match event {
-            Some(NikaEvent::Key(key_event)) => self.handle_key_events(key_event)?,
+            Some(NikaEvent::Key(key_event)) => match keymap.action(self.context(), key_event) {
+                // Back goes back everywhere, unless a page needs it for something else.
+                Some(Action::Back) => self.back(),
+                Some(action) => self.handle_action(action)?,
+                None => self.handle_key_events(key_event)?,
+            },
             Some(NikaEvent::Render) => Some(NikaAction::Render),
             _ => None,
         };
         Ok(r)
     }
 
+    /// Does what a key bound in the page's context asks for.
+    #[allow(unused_variables)]
+    fn handle_action(&mut self, action: Action) -> io::Result<Option<NikaAction>>;
+
+    /// Keys that aren't bound to anything, e.g. to type them.
     #[allow(unused_variables)]
-    fn handle_key_events(&mut self, key: KeyEvent) -> io::Result<Option<NikaAction>>;
+    fn handle_key_events(&mut self, key: KeyEvent) -> io::Result<Option<NikaAction>> {
+        Ok(None)
+    }
+
+    /// What the Back key does, which is going to the previous page unless overridden.
+    fn back(&mut self) -> Option<NikaAction> {
+        Some(NikaAction::Back)
+    }
+
+    /// The operation the page is waiting for, if it's a loading screen.
+    fn operation(&self) -> Option<&Operation> {
+        None
+    }
 
     #[allow(unused_variables)]
     fn update(&mut self, action: NikaAction) -> anyhow::Result<()>;
 
-    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect);
+    /// `keymap` is for the key hints.
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect, theme: &Theme, keymap: &Keymap);
 }
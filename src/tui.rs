@@ -27,6 +27,7 @@ pub struct Tui {
     pub terminal: Terminal<CrosstermBackend<std::io::Stdout>>,
     pub event_rx: UnboundedReceiver<NikaEvent>,
     pub event_tx: UnboundedSender<NikaEvent>,
+    #[allow(dead_code)]
     pub framerate: f64,
 }
 
@@ -71,6 +72,7 @@ impl Tui {
                 let crossterm_event = reader.next().fuse();
                 tokio::select! { // Checks the type of some event and sends it through tx.
                     maybe_event = crossterm_event => {
+                        #[allow(clippy::collapsible_match)]
                         match maybe_event {
                             Some(Ok(evt)) => {
                                 if let Event::Key(key) = evt {
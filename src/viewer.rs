@@ -0,0 +1,167 @@
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// Replaced with the chapter's directory.
+pub const DIR_PLACEHOLDER: &str = "{dir}";
+/// Replaced with the first page of the chapter.
+pub const FIRST_PAGE_PLACEHOLDER: &str = "{first}";
+/// Must be a whole argument; expands to one argument per page, in reading order.
+pub const PAGES_PLACEHOLDER: &str = "{pages}";
+
+/// External program used to open downloaded chapters.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ViewerConfig {
+    pub command: String,
+    pub args: Vec<String>,
+    /// Whether to stay on the loading screen until the viewer exits.
+    pub wait: bool,
+}
+
+impl Default for ViewerConfig {
+    fn default() -> Self {
+        Self {
+            command: String::from("feh"),
+            args: vec![DIR_PLACEHOLDER.to_owned()],
+            wait: false,
+        }
+    }
+}
+
+impl ViewerConfig {
+    /// Expands the argument template for the chapter stored in `dir`.
+    pub fn build_args(&self, dir: &Path) -> io::Result<Vec<String>> {
+        let pages = pages(dir)?;
+        let pages: Vec<String> = pages.iter().map(|p| p.display().to_string()).collect();
+        let dir = dir.display().to_string();
+        let first = pages.first().cloned().unwrap_or_default();
+
+        let mut args = Vec::with_capacity(self.args.len());
+        for arg in &self.args {
+            if arg == PAGES_PLACEHOLDER {
+                args.extend(pages.iter().cloned());
+            } else {
+                args.push(
+                    arg.replace(DIR_PLACEHOLDER, &dir)
+                        .replace(FIRST_PAGE_PLACEHOLDER, &first),
+                );
+            }
+        }
+
+        Ok(args)
+    }
+
+    /// Runs the viewer on a chapter and waits for it to exit, failing if it couldn't be started
+    /// or exited unsuccessfully.
+    pub async fn open(&self, dir: &Path) -> anyhow::Result<()> {
+        let args = self.build_args(dir)?;
+
+        // Output is captured so it doesn't draw over the TUI.
+        let output = match Command::new(&self.command).args(args).output().await {
+            Ok(output) => output,
+            Err(e) => bail!("Couldn't start viewer \"{}\": {e}", self.command),
+        };
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(
+                "Viewer \"{}\" exited with {}: {}",
+                self.command,
+                output.status,
+                stderr.trim()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Pages of a chapter in reading order. Files are compared by the number in their name, so
+/// page-10 comes after page-9.
+pub fn pages(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut pages: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+
+    pages.sort_by_cached_key(|p| {
+        let name = p
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let number: Option<usize> = name
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|s| !s.is_empty())
+            .and_then(|s| s.parse().ok());
+        (number, name)
+    });
+
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::ViewerConfig;
+
+    #[test]
+    fn expands_placeholders_in_page_order() {
+        let dir = env::temp_dir().join("nika-viewer-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        for i in [10, 2, 1] {
+            fs::write(dir.join(format!("page-{i}.jpeg")), []).unwrap();
+        }
+
+        let viewer = ViewerConfig {
+            command: String::from("imv"),
+            args: vec!["--dir={dir}".into(), "{first}".into(), "{pages}".into()],
+            wait: true,
+        };
+        let page = |i: usize| dir.join(format!("page-{i}.jpeg")).display().to_string();
+
+        assert_eq!(
+            viewer.build_args(&dir).unwrap(),
+            [
+                format!("--dir={}", dir.display()),
+                page(1),
+                page(1),
+                page(2),
+                page(10)
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_spawn_failures_and_exit_codes() {
+        let dir = env::temp_dir();
+        let viewer = |command: &str| ViewerConfig {
+            command: command.to_owned(),
+            args: Vec::new(),
+            wait: true,
+        };
+
+        let missing = viewer("nika-viewer-that-does-not-exist").open(&dir).await;
+        assert!(
+            missing
+                .unwrap_err()
+                .to_string()
+                .contains("Couldn't start viewer")
+        );
+
+        let failing = viewer("false").open(&dir).await;
+        assert!(failing.unwrap_err().to_string().contains("exited with"));
+
+        assert!(viewer("true").open(&dir).await.is_ok());
+    }
+}